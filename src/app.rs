@@ -2,147 +2,1017 @@ use std::time::{Duration, Instant};
 
 use anyhow::{Error, Result};
 use sdl2::{
-    event::Event,
-    rect::Point,
-    render::{Canvas, Texture, TextureCreator},
-    video::{Window, WindowContext},
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    mouse::MouseButton,
+    pixels::Color,
+    pixels::PixelFormatEnum,
+    rect::{Point, Rect},
+    render::{Canvas, TextureCreator},
+    video::{FullscreenType, Window, WindowContext},
     Sdl,
 };
 
 use crate::{
-    config::{
-        CLEAR_COLOR, PIXEL_FORMAT, STEP_TIME, TILE_SIZE, WINDOW_HEIGHT, WINDOW_TITLE, WINDOW_WIDTH,
-    },
-    NewWorldParams, World,
+    camera::Camera,
+    capture,
+    clock::SimulationClock,
+    config::{Config, ResizePolicy, WyrmStyle, PIXEL_FORMAT},
+    save_image, timestamped_path,
+    tool::{self, MAX_BRUSH_SIZE},
+    Corner, Damage, Direction, Game, GameMode, GameState, GifRecording, HeatmapKind, Hud, HudItem,
+    HudPanel, Inspector, Map, NewGifRecordingParams, Pathfinder, SdlRenderer, Theme, Tool, World,
+    Wyrm, BUILTIN_THEMES, HEATMAP_KINDS,
 };
 
+const MIN_SPEED_LEVEL: i32 = -3;
+const MAX_SPEED_LEVEL: i32 = 4;
+const FAST_FORWARD_FRAME_TIME: Duration = Duration::from_millis(16);
+const ZOOM_STEP: f64 = 1.25;
+const PAN_STEP: i32 = 32;
+const SELECTION_COLOR: Color = Color::WHITE;
+// Wyrm segments get gaps and heads get a direction marker from this many
+// pixels per tile.
+const DETAIL_ZOOM: f64 = 4.0;
+// Heatmaps need an alpha channel so tiles without events stay transparent.
+const HEATMAP_PIXEL_FORMAT: PixelFormatEnum = PixelFormatEnum::RGBA32;
+const HEATMAP_ALPHA: u8 = 200;
+const HEATMAPS_DISABLED: &str = "heatmaps are off; set `heatmaps = true` to count them";
+// How long messages such as saved file paths stay on screen.
+const STATUS_DURATION: Duration = Duration::from_secs(4);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Playback {
+    Running,
+    Paused,
+    FastForward,
+}
+
 pub struct App {
-    #[allow(dead_code)]
+    config: Config,
     width: u16,
-    #[allow(dead_code)]
     height: u16,
-    pitch: usize,
-    display_ratio: u32,
-    tile_size: u32,
     sdl_context: Sdl,
     canvas: Canvas<Window>,
-    #[allow(dead_code)]
     texture_creator: TextureCreator<WindowContext>,
-    texture: Texture,
-    pixel_data: Vec<u8>,
+    renderer: SdlRenderer,
     world: World,
-    last_step_time: Option<Instant>,
-    elapsed_time: Duration,
+    camera: Camera,
+    hud: Hud,
+    hud_visible: bool,
+    tool: Tool,
+    brush_size: u32,
+    stroke: Option<Point>,
+    drawing: Vec<Point>,
+    inspector: Inspector,
+    mouse_position: Point,
+    panning: bool,
+    clock: SimulationClock,
+    title: String,
+    playback: Playback,
+    speed_level: i32,
+    recording: Option<GifRecording>,
+    heatmap_overlay: Option<HeatmapKind>,
+    heatmap_renderer: SdlRenderer,
+    // The kind and maximum count last drawn by `heatmap_renderer`.
+    heatmap_shown: Option<(HeatmapKind, u32)>,
+    status: Option<(String, Instant)>,
+    game: Option<Game>,
 }
 
 impl App {
-    pub fn new() -> Result<App> {
+    pub fn new(config: Config, map: Option<&Map>) -> Result<App> {
         let sdl_context = sdl2::init().map_err(Error::msg)?;
         let video_subsystem = sdl_context.video().map_err(Error::msg)?;
 
         sdl2::hint::set("SDL_HINT_RENDER_SCALE_QUALITY", "nearest");
 
-        let window = video_subsystem
-            .window(WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)
+        let mut window_builder = video_subsystem.window(
+            &config.window_title,
+            config.window_width,
+            config.window_height,
+        );
+        window_builder
             .position_centered()
             .allow_highdpi()
-            .build()?;
-        let (window_width, window_height) = window.drawable_size();
+            .resizable();
+        if config.fullscreen {
+            window_builder.fullscreen_desktop();
+        }
 
+        let window = window_builder.build()?;
         let canvas = window.into_canvas().accelerated().present_vsync().build()?;
-        let display_ratio = window_width / WINDOW_WIDTH;
 
-        let tile_size = TILE_SIZE * display_ratio;
-        let width = u16::try_from(window_width / tile_size)?;
-        let height = u16::try_from(window_height / tile_size)?;
+        let mut world = World::from_config(&config, map)?;
+        let game = match config.game {
+            GameMode::Off => None,
+            mode => Some(Game::new(&mut world, mode, &config)?),
+        };
+        let (width, height) = (world.width, world.height);
 
-        let pitch = PIXEL_FORMAT.byte_size_of_pixels(width.into());
-        let byte_size = PIXEL_FORMAT.byte_size_from_pitch_and_height(pitch, height.into());
-        let pixel_data = vec![0u8; byte_size];
+        let clock = SimulationClock::new(config.step_time(), config.max_catch_up_steps);
 
-        let texture_creator = canvas.texture_creator();
-        let texture =
-            texture_creator.create_texture_streaming(PIXEL_FORMAT, width.into(), height.into())?;
+        let output_size = canvas.output_size().map_err(Error::msg)?;
+        let camera = Camera::fit((width, height), output_size);
 
-        let world = World::new(&NewWorldParams { width, height });
+        let texture_creator = canvas.texture_creator();
+        let hud = Hud::new(&texture_creator, output_size, config.hud_scale)?;
+        let hud_visible = config.hud;
+        let renderer = SdlRenderer::new(&canvas, PIXEL_FORMAT)?;
+        let heatmap_renderer = SdlRenderer::overlay(&canvas, HEATMAP_PIXEL_FORMAT, HEATMAP_ALPHA)?;
 
         Ok(App {
+            config,
             width,
             height,
-            pitch,
-            display_ratio,
-            tile_size,
             sdl_context,
             canvas,
             texture_creator,
-            texture,
-            pixel_data,
+            renderer,
             world,
-            last_step_time: None,
-            elapsed_time: Duration::ZERO,
+            camera,
+            hud,
+            hud_visible,
+            tool: Tool::Spawn,
+            brush_size: 1,
+            stroke: None,
+            drawing: Vec::new(),
+            inspector: Inspector::default(),
+            mouse_position: Point::new(0, 0),
+            panning: false,
+            clock,
+            title: String::new(),
+            playback: Playback::Running,
+            speed_level: 0,
+            recording: None,
+            heatmap_overlay: None,
+            heatmap_renderer,
+            heatmap_shown: None,
+            status: None,
+            game,
         })
     }
 
-    #[allow(clippy::cast_possible_wrap)]
-    #[allow(clippy::cast_sign_loss)]
-    fn world_coordinate_from_screen(&self, x: i32) -> i32 {
-        x * self.display_ratio as i32 / self.tile_size as i32
+    fn resize_world(&mut self, width: u16, height: u16) -> Result<()> {
+        // Every frame of a GIF has the same size, so a resize ends the recording.
+        self.stop_recording()?;
+        self.world.resize(width, height);
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    fn handle_window_resize(&mut self, window_width: i32, window_height: i32) -> Result<()> {
+        if self.config.resize_policy == ResizePolicy::Resize {
+            let tile_size = i32::try_from(self.config.tile_size)?;
+            let width = u16::try_from((window_width / tile_size).max(3))?;
+            let height = u16::try_from((window_height / tile_size).max(3))?;
+            if (width, height) != (self.width, self.height) {
+                self.resize_world(width, height)?;
+            }
+        }
+
+        self.fit_camera()
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<()> {
+        let window = self.canvas.window_mut();
+        let fullscreen_type = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        window.set_fullscreen(fullscreen_type).map_err(Error::msg)
+    }
+
+    fn output_size(&self) -> Result<(u32, u32)> {
+        self.canvas.output_size().map_err(Error::msg)
+    }
+
+    fn display_ratio(&self) -> Result<f64> {
+        let (window_width, _) = self.canvas.window().size();
+        let (output_width, _) = self.output_size()?;
+        Ok(f64::from(output_width) / f64::from(window_width))
+    }
+
+    fn fit_camera(&mut self) -> Result<()> {
+        self.camera = Camera::fit((self.width, self.height), self.output_size()?);
+        Ok(())
+    }
+
+    fn zoom_camera(&mut self, factor: f64) -> Result<()> {
+        let ratio = self.display_ratio()?;
+        let anchor = (
+            f64::from(self.mouse_position.x()) * ratio,
+            f64::from(self.mouse_position.y()) * ratio,
+        );
+        self.camera.zoom_at(factor, anchor, self.output_size()?);
+        Ok(())
+    }
+
+    fn pan_camera(&mut self, dx: i32, dy: i32) -> Result<()> {
+        let ratio = self.display_ratio()?;
+        self.camera
+            .pan(f64::from(dx) * ratio, f64::from(dy) * ratio);
+        self.camera.clamp_to((self.width, self.height));
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn world_point_from_screen(&self, x: i32, y: i32) -> Result<Point> {
+        let ratio = self.display_ratio()?;
+        let screen_position = (f64::from(x) * ratio, f64::from(y) * ratio);
+        let (world_x, world_y) = self
+            .camera
+            .screen_to_world(screen_position, self.output_size()?);
+        Ok(Point::new(world_x.floor() as i32, world_y.floor() as i32))
+    }
+
+    fn world_position_from_screen(&self, x: i32, y: i32) -> Result<Option<Point>> {
+        let position = self.world_point_from_screen(x, y)?;
+        Ok(self.world.contains(position).then_some(position))
+    }
+
+    fn apply_brush(&mut self, center: Point) -> Result<()> {
+        for position in tool::brush_positions(center, self.brush_size) {
+            if let Some(tile) = self.tool.paint_tile() {
+                self.world.paint_tile(position, tile);
+            } else if self.tool == Tool::Kill {
+                self.world.kill_wyrm_at(position)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn continue_stroke(&mut self, x: i32, y: i32) -> Result<()> {
+        let Some(last) = self.stroke else {
+            return Ok(());
+        };
+
+        let position = self.world_point_from_screen(x, y)?;
+        if position != last {
+            for center in tool::line_positions(last, position).into_iter().skip(1) {
+                self.apply_brush(center)?;
+            }
+            self.stroke = Some(position);
+        }
+
+        Ok(())
     }
 
     fn render(&mut self) -> Result<()> {
-        self.world.render(&mut self.pixel_data[..]);
-        self.texture
-            .update(None, &self.pixel_data[..], self.pitch)?;
+        self.world.render_changes(&mut self.renderer)?;
 
-        self.canvas.set_draw_color(CLEAR_COLOR);
+        self.canvas.set_draw_color(self.config.clear_color);
         self.canvas.clear();
-        self.canvas
-            .copy(&self.texture, None, None)
-            .map_err(Error::msg)?;
+        let visible_rects = self
+            .camera
+            .visible_rects((self.width, self.height), self.output_size()?);
+        if let Some((source, destination)) = visible_rects {
+            self.canvas
+                .copy(&self.renderer.texture, source, destination)
+                .map_err(Error::msg)?;
+        }
+
+        self.draw_wyrm_details()?;
+        if let (Some(kind), Some((source, destination))) = (self.heatmap_overlay, visible_rects) {
+            self.draw_heatmap(kind, source, destination)?;
+        }
+        self.draw_selection()?;
+
+        let mut panels = Vec::new();
+        if self.hud_visible {
+            panels.push(HudPanel::new(Corner::TopLeft, self.hud_lines()));
+        }
+        panels.extend(self.inspector.panel(&self.world));
+        if let Some(game) = &self.game {
+            panels.extend(game.panels(&self.world));
+        }
+        self.status = self
+            .status
+            .take()
+            .filter(|(_, shown_at)| shown_at.elapsed() < STATUS_DURATION);
+        if let Some((message, _)) = &self.status {
+            panels.push(HudPanel::new(Corner::BottomRight, vec![message.clone()]));
+        }
+        self.hud
+            .render(&mut self.canvas, &self.texture_creator, panels)?;
         self.canvas.present();
         Ok(())
     }
 
-    fn step(&mut self) -> Result<()> {
-        let needs_update = if let Some(last_step_time) = self.last_step_time {
-            let elapsed_time = last_step_time.elapsed();
-            self.elapsed_time += elapsed_time;
-            elapsed_time >= STEP_TIME
+    // Blends the counts of one heatmap over the world, leaving tiles without
+    // any events untouched. Only tiles whose counts changed are redrawn,
+    // unless the kind or its maximum changed, which recolors every tile.
+    fn draw_heatmap(&mut self, kind: HeatmapKind, source: Rect, destination: Rect) -> Result<()> {
+        let Some(heatmap) = &mut self.world.heatmap else {
+            return Ok(());
+        };
+
+        let mut damage = heatmap.take_damage();
+        let shown = Some((kind, heatmap.max(kind)));
+        if self.heatmap_shown != shown {
+            damage = Damage::Full;
+            self.heatmap_shown = shown;
+        }
+        heatmap.draw(kind, &mut self.heatmap_renderer, &damage)?;
+        self.canvas
+            .copy(&self.heatmap_renderer.texture, source, destination)
+            .map_err(Error::msg)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn outline_tiles(&mut self, tiles: &[Point]) -> Result<()> {
+        let output_size = self.output_size()?;
+        let zoom = self.camera.zoom.max(1.0).round() as u32;
+        let rects: Vec<Rect> = tiles
+            .iter()
+            .map(|tile| {
+                let world_position = (f64::from(tile.x()), f64::from(tile.y()));
+                let (x, y) = self.camera.world_to_screen(world_position, output_size);
+                Rect::new(x.round() as i32, y.round() as i32, zoom, zoom)
+            })
+            .collect();
+
+        self.canvas.set_draw_color(SELECTION_COLOR);
+        self.canvas.draw_rects(&rects).map_err(Error::msg)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn tile_screen_rect(&self, tile: Point, output_size: (u32, u32)) -> Rect {
+        let (x0, y0) = (f64::from(tile.x()), f64::from(tile.y()));
+        let (sx0, sy0) = self.camera.world_to_screen((x0, y0), output_size);
+        let (sx1, sy1) = self
+            .camera
+            .world_to_screen((x0 + 1.0, y0 + 1.0), output_size);
+        Rect::new(
+            sx0.round() as i32,
+            sy0.round() as i32,
+            (sx1.round() - sx0.round()).max(1.0) as u32,
+            (sy1.round() - sy0.round()).max(1.0) as u32,
+        )
+    }
+
+    // Redraws visible wyrms on top of the world texture with a gap around
+    // each segment, bridged only between consecutive segments, so bodies that
+    // touch or cross stay distinguishable. Heads get a marker on the side the
+    // wyrm is facing.
+    fn draw_wyrm_details(&mut self) -> Result<()> {
+        if self.world.config.wyrm_style != WyrmStyle::Shaded || self.camera.zoom < DETAIL_ZOOM {
+            return Ok(());
+        }
+
+        let output_size = self.output_size()?;
+        let Some((visible, _)) = self
+            .camera
+            .visible_rects((self.width, self.height), output_size)
+        else {
+            return Ok(());
+        };
+
+        let mut wyrm_ids: Vec<u16> = self.world.wyrms.keys().copied().collect();
+        wyrm_ids.sort_unstable();
+        for wyrm_id in wyrm_ids {
+            let wyrm = &self.world.wyrms[&wyrm_id];
+            let segments: Vec<Point> = wyrm.segments.iter().copied().collect();
+            let direction = wyrm.direction;
+
+            for (index, &segment) in segments.iter().enumerate() {
+                if !visible.contains_point(segment) {
+                    continue;
+                }
+                let color = self.world.segment_color(wyrm, index);
+
+                let rect = self.tile_screen_rect(segment, output_size);
+                let gap = (rect.width().min(rect.height()) / 8).max(1);
+                let inset = Rect::new(
+                    rect.x() + gap.cast_signed(),
+                    rect.y() + gap.cast_signed(),
+                    rect.width().saturating_sub(2 * gap).max(1),
+                    rect.height().saturating_sub(2 * gap).max(1),
+                );
+
+                let mut body = vec![inset];
+                let neighbors = [index.checked_sub(1), Some(index + 1)];
+                for neighbor in neighbors.into_iter().flatten() {
+                    let Some(&other) = segments.get(neighbor) else {
+                        continue;
+                    };
+                    if let Some(side) = Direction::between(segment, other) {
+                        body.push(bridge_rect(inset, rect, side));
+                    }
+                }
+
+                self.canvas.set_draw_color(self.world.config.theme.empty);
+                self.canvas.fill_rect(rect).map_err(Error::msg)?;
+                self.canvas.set_draw_color(color);
+                self.canvas.fill_rects(&body).map_err(Error::msg)?;
+
+                if index == 0 {
+                    self.canvas.set_draw_color(self.world.config.theme.empty);
+                    self.canvas
+                        .fill_rect(head_marker_rect(inset, direction))
+                        .map_err(Error::msg)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_selection(&mut self) -> Result<()> {
+        if !self.drawing.is_empty() {
+            self.outline_tiles(&self.drawing.clone())?;
+        }
+
+        // Mark the players' heads so they can be found among the other wyrms.
+        let player_heads: Vec<Point> = self
+            .game
+            .iter()
+            .flat_map(|game| &game.players)
+            .filter_map(|player| player.wyrm_id)
+            .filter_map(|wyrm_id| self.world.wyrms.get(&wyrm_id))
+            .map(Wyrm::head)
+            .collect();
+        if !player_heads.is_empty() {
+            self.outline_tiles(&player_heads)?;
+        }
+
+        let Some(wyrm) = self.inspector.selected_wyrm(&self.world) else {
+            return Ok(());
+        };
+        let segments: Vec<Point> = wyrm.segments.iter().copied().collect();
+        self.outline_tiles(&segments)
+    }
+
+    fn playback_state(&self) -> String {
+        match self.playback {
+            Playback::Paused => "paused".to_string(),
+            Playback::FastForward => "fast-forward".to_string(),
+            Playback::Running => format!("{}x", self.speed()),
+        }
+    }
+
+    fn hud_lines(&self) -> Vec<String> {
+        let frame_time = self.clock.frame_time().as_secs_f64();
+        let frames_per_second = if frame_time > 0.0 {
+            1.0 / frame_time
         } else {
-            true
+            0.0
         };
 
-        if needs_update {
-            self.last_step_time = Some(Instant::now());
+        self.config
+            .hud_items
+            .iter()
+            .map(|item| match item {
+                HudItem::Step => format!("step: {}", self.world.current_step()),
+                HudItem::Population => format!("wyrms: {}", self.world.wyrms.len()),
+                HudItem::Speed => format!("speed: {}", self.playback_state()),
+                HudItem::StepsPerSecond => {
+                    format!("steps/s: {:.1}", self.clock.steps_per_second())
+                }
+                HudItem::FramesPerSecond => format!("fps: {frames_per_second:.1}"),
+                HudItem::Tool if self.tool.is_brush() => {
+                    format!("tool: {} (size {})", self.tool.name(), self.brush_size)
+                }
+                HudItem::Tool => format!("tool: {}", self.tool.name()),
+            })
+            .chain(
+                self.recording
+                    .as_ref()
+                    .map(|recording| format!("recording: {} frames", recording.frames)),
+            )
+            .chain(
+                self.heatmap_overlay
+                    .zip(self.world.heatmap.as_ref())
+                    .map(|(kind, heatmap)| {
+                        format!("heatmap: {} (max {})", kind.name(), heatmap.max(kind))
+                    }),
+            )
+            .collect()
+    }
+
+    fn speed(&self) -> f64 {
+        2f64.powi(self.speed_level)
+    }
+
+    fn update_title(&mut self) -> Result<()> {
+        let state = self.playback_state();
+        let title = format!(
+            "{} - step {} - {state} - {:.1} steps/s - {:.1} ms/frame",
+            self.config.window_title,
+            self.world.current_step(),
+            self.clock.steps_per_second(),
+            self.clock.frame_time().as_secs_f64() * 1000.0,
+        );
+
+        if title != self.title {
+            self.canvas.window_mut().set_title(&title)?;
+            self.title = title;
+        }
+
+        Ok(())
+    }
+
+    fn set_speed_level(&mut self, speed_level: i32) {
+        self.speed_level = speed_level.clamp(MIN_SPEED_LEVEL, MAX_SPEED_LEVEL);
+        if self.playback == Playback::FastForward {
+            self.playback = Playback::Running;
+        }
+        let step_time = self.config.step_time().div_f64(self.speed());
+        self.clock.set_step_time(step_time);
+    }
+
+    fn toggle_paused(&mut self) {
+        self.playback = match self.playback {
+            Playback::Paused => Playback::Running,
+            _ => Playback::Paused,
+        };
+        self.clock.reset();
+    }
+
+    fn toggle_fast_forward(&mut self) {
+        self.playback = match self.playback {
+            Playback::FastForward => Playback::Running,
+            _ => Playback::FastForward,
+        };
+        self.clock.reset();
+    }
+
+    fn single_step(&mut self) -> Result<()> {
+        if self.playback == Playback::Paused {
             self.world.step()?;
-            self.render()?;
+            self.clock.record_steps(1);
         }
 
         Ok(())
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        let mut event_pump = self.sdl_context.event_pump().map_err(Error::msg)?;
+    // Runs as many steps as fit in one frame; only the last one is rendered.
+    fn fast_forward_steps(&mut self) -> Result<u32> {
+        let start = Instant::now();
+        let mut steps = 0;
+        while start.elapsed() < FAST_FORWARD_FRAME_TIME {
+            self.world.step()?;
+            steps += 1;
+        }
+
+        Ok(steps)
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        let steps = match self.playback {
+            Playback::Paused => 0,
+            Playback::FastForward => self.fast_forward_steps()?,
+            Playback::Running => {
+                let steps = self.clock.tick();
+                for _ in 0..steps {
+                    self.world.step()?;
+                }
+                steps
+            }
+        };
+
+        self.clock.record_steps(steps);
+        Ok(())
+    }
+
+    fn frame(&mut self) -> Result<()> {
+        self.advance()?;
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.world)?;
+        }
+
+        let deaths = self.world.take_deaths();
+        self.inspector.record_deaths(&deaths);
+        if let Some(game) = &mut self.game {
+            game.update(&mut self.world, &deaths)?;
+        }
+        if let Some(head) = self.inspector.followed_position(&self.world) {
+            self.camera.x = f64::from(head.x()) + 0.5;
+            self.camera.y = f64::from(head.y()) + 0.5;
+        }
 
         self.render()?;
+        self.clock.record_frame();
+        self.update_title()
+    }
 
-        'running: loop {
-            for event in event_pump.poll_iter() {
-                match event {
-                    Event::Quit { .. } => break 'running,
-                    Event::MouseButtonUp { x, y, .. } => {
-                        let world_x = self.world_coordinate_from_screen(x);
-                        let world_y = self.world_coordinate_from_screen(y);
-                        let position = Point::new(world_x, world_y);
-                        self.world.create_wyrm(position)?;
+    // Extends the wyrm being drawn one orthogonal step at a time towards the
+    // cursor. Stepping back onto the previous segment undoes the last step,
+    // and the path only crosses empty tiles, never its own segments. A click
+    // on food spawns a wyrm there, but can't be dragged into a longer one.
+    fn continue_drawing(&mut self, x: i32, y: i32) -> Result<()> {
+        let Some(&last) = self.drawing.last() else {
+            return Ok(());
+        };
+        if !self.world.is_vacant(self.drawing[0])? {
+            return Ok(());
+        }
+
+        let target = self.world_point_from_screen(x, y)?;
+        let mut current = last;
+        while current != target {
+            let dx = target.x() - current.x();
+            let dy = target.y() - current.y();
+            let next = if dx.abs() >= dy.abs() {
+                current.offset(dx.signum(), 0)
+            } else {
+                current.offset(0, dy.signum())
+            };
+
+            let len = self.drawing.len();
+            if len >= 2 && self.drawing[len - 2] == next {
+                self.drawing.pop();
+            } else if self.drawing.contains(&next)
+                || !self.world.contains(next)
+                || !self.world.is_vacant(next)?
+            {
+                break;
+            } else {
+                self.drawing.push(next);
+            }
+            current = next;
+        }
+
+        Ok(())
+    }
+
+    fn finish_drawing(&mut self) -> Result<()> {
+        let mut segments = std::mem::take(&mut self.drawing);
+        match segments.len() {
+            0 => {}
+            1 => {
+                self.world.create_wyrm(segments[0])?;
+            }
+            len => {
+                let direction = Direction::between(segments[len - 2], segments[len - 1])
+                    .expect("drawn segments are adjacent");
+                segments.reverse();
+                self.world.create_wyrm_from_segments(&segments, direction)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Result<()> {
+        match *event {
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } => self.panning = true,
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } => self.panning = false,
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.tool.is_brush() => {
+                let position = self.world_point_from_screen(x, y)?;
+                self.stroke = Some(position);
+                self.apply_brush(position)?;
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.tool == Tool::Spawn => {
+                let Some(position) = self.world_position_from_screen(x, y)? else {
+                    return Ok(());
+                };
+                if self.world.can_spawn_on(position)? {
+                    self.drawing = vec![position];
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } if self.stroke.is_some() => self.stroke = None,
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } if self.tool == Tool::Spawn => self.finish_drawing()?,
+            Event::MouseMotion {
+                x, y, xrel, yrel, ..
+            } => {
+                self.mouse_position = Point::new(x, y);
+                if self.panning {
+                    self.pan_camera(-xrel, -yrel)?;
+                }
+                self.continue_stroke(x, y)?;
+                self.continue_drawing(x, y)?;
+            }
+            Event::MouseWheel { y, .. } if y != 0 => {
+                self.zoom_camera(ZOOM_STEP.powi(y))?;
+            }
+            Event::MouseButtonUp {
+                mouse_btn, x, y, ..
+            } => {
+                let Some(position) = self.world_position_from_screen(x, y)? else {
+                    return Ok(());
+                };
+
+                if mouse_btn == MouseButton::Right {
+                    // Pathfinders would change the rules of a game.
+                    if self.tool == Tool::Spawn && self.game.is_none() {
+                        let budget = self.world.config.pathfinder_budget;
+                        let brain = Box::new(Pathfinder::new(budget));
+                        self.world.create_wyrm_with_brain(position, brain)?;
                     }
-                    _ => {}
+                    return Ok(());
                 }
+
+                if self.tool == Tool::Inspect {
+                    self.inspector.select(&self.world, position);
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat,
+                ..
+            } => self.handle_key(keycode, repeat)?,
+            Event::Window {
+                win_event: WindowEvent::SizeChanged(width, height),
+                ..
+            } => self.handle_window_resize(width, height)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn toggle_recording(&mut self) -> Result<()> {
+        if self.recording.is_some() {
+            return self.stop_recording();
+        }
+
+        let path = timestamped_path(&self.config.recording_dir, "gif");
+        let mut recording = GifRecording::create(&NewGifRecordingParams {
+            path: &path,
+            world_size: (self.width, self.height),
+            scale: self.config.recording_scale_factor(),
+            frame_skip: self.config.recording_frame_skip,
+            step_time: self.config.step_time(),
+        })?;
+        recording.record(&self.world)?;
+        self.recording = Some(recording);
+        self.show_status(format!("recording to {}", path.display()));
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<()> {
+        if let Some(recording) = self.recording.take() {
+            let frames = recording.frames;
+            recording.finish()?;
+            self.show_status(format!("recording stopped after {frames} frames"));
+        }
+
+        Ok(())
+    }
+
+    // Shows a short message in the bottom right corner for a few seconds.
+    fn show_status(&mut self, message: String) {
+        self.status = Some((message, Instant::now()));
+    }
+
+    fn take_screenshot(&mut self) -> Result<()> {
+        let format = self.config.screenshot_format;
+        let path = timestamped_path(&self.config.screenshot_dir, format.extension());
+        let scale = self
+            .config
+            .screenshot_scale_factor((self.width, self.height));
+        let image = capture(&self.world, scale)?;
+        save_image(&image, &path, format)?;
+        self.show_status(format!("saved screenshot to {}", path.display()));
+        Ok(())
+    }
+
+    // Switches to the next built-in theme, or to the configured theme if it
+    // was loaded from a file.
+    fn cycle_theme(&mut self) -> Result<()> {
+        let configured = &self.config.world.theme;
+        let mut themes: Vec<Theme> = BUILTIN_THEMES
+            .iter()
+            .filter_map(|name| Theme::builtin(name))
+            .collect();
+        if Theme::builtin(&configured.name).is_none() {
+            themes.push(configured.clone());
+        }
+
+        let current = &self.world.config.theme.name;
+        let index = themes
+            .iter()
+            .position(|theme| &theme.name == current)
+            .map_or(0, |index| (index + 1) % themes.len());
+        let theme = themes.swap_remove(index);
+        self.show_status(format!("theme: {}", theme.name));
+        self.world.set_theme(theme)
+    }
+
+    // Steps through the heatmap overlays, then back to none.
+    fn cycle_heatmap_overlay(&mut self) {
+        if self.world.heatmap.is_none() {
+            self.show_status(HEATMAPS_DISABLED.to_string());
+            return;
+        }
+
+        let next = match self.heatmap_overlay {
+            None => Some(0),
+            Some(kind) => HEATMAP_KINDS
+                .iter()
+                .position(|&other| other == kind)
+                .map(|index| index + 1)
+                .filter(|&index| index < HEATMAP_KINDS.len()),
+        };
+        self.heatmap_overlay = next.map(|index| HEATMAP_KINDS[index]);
+        if self.heatmap_overlay.is_none() {
+            self.heatmap_shown = None;
+            if let Some(heatmap) = &mut self.world.heatmap {
+                heatmap.stop_tracking_damage();
             }
+        }
+    }
+
+    // Saves the shown heatmap (or visits, if none is shown) as an image and
+    // every counter as CSV, next to the screenshots.
+    fn export_heatmap(&mut self) -> Result<()> {
+        let Some(heatmap) = &self.world.heatmap else {
+            self.show_status(HEATMAPS_DISABLED.to_string());
+            return Ok(());
+        };
+        let kind = self.heatmap_overlay.unwrap_or(HeatmapKind::Visits);
+        let format = self.config.screenshot_format;
+        let directory = &self.config.screenshot_dir;
+        let scale = self
+            .config
+            .screenshot_scale_factor((self.width, self.height));
+
+        let image_path = timestamped_path(
+            directory,
+            &format!("{}.{}", kind.name(), format.extension()),
+        );
+        heatmap.save(kind, scale, &image_path)?;
+        let csv_path = timestamped_path(directory, "heatmap.csv");
+        heatmap.save_csv(&csv_path)?;
+        self.show_status(format!(
+            "saved {} heatmap to {} and counts to {}",
+            kind.name(),
+            image_path.display(),
+            csv_path.display()
+        ));
+        Ok(())
+    }
+
+    // Turns a player's wyrm while a game is running: the arrow keys steer
+    // the only player in snake mode and the second one in versus mode, where
+    // WASD steers the first. Returns false when the key should do its usual
+    // job instead.
+    fn steer_player(&self, keycode: Keycode) -> bool {
+        let Some(game) = &self.game else {
+            return false;
+        };
+        if game.state != GameState::Playing {
+            return false;
+        }
 
-            self.step()?;
+        let arrows_player = match game.mode {
+            GameMode::Versus => 1,
+            _ => 0,
+        };
+        let (player, direction) = match keycode {
+            Keycode::Left => (arrows_player, Direction::Left),
+            Keycode::Right => (arrows_player, Direction::Right),
+            Keycode::Up => (arrows_player, Direction::Up),
+            Keycode::Down => (arrows_player, Direction::Down),
+            Keycode::A if game.mode == GameMode::Versus => (0, Direction::Left),
+            Keycode::D if game.mode == GameMode::Versus => (0, Direction::Right),
+            Keycode::W if game.mode == GameMode::Versus => (0, Direction::Up),
+            Keycode::S if game.mode == GameMode::Versus => (0, Direction::Down),
+            _ => return false,
+        };
+        let Some(player) = game.players.get(player) else {
+            return false;
+        };
+        player.controls.steer(direction);
+        true
+    }
+
+    fn proceed_game(&mut self) -> Result<()> {
+        match &mut self.game {
+            Some(game) => game.proceed(&mut self.world),
+            None => Ok(()),
+        }
+    }
+
+    fn select_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+        self.stroke = None;
+        self.drawing.clear();
+    }
+
+    fn handle_key(&mut self, keycode: Keycode, repeat: bool) -> Result<()> {
+        match keycode {
+            Keycode::Space if !repeat => self.toggle_paused(),
+            Keycode::Period => self.single_step()?,
+            Keycode::Equals | Keycode::Plus | Keycode::KpPlus => {
+                self.set_speed_level(self.speed_level + 1);
+            }
+            Keycode::Minus | Keycode::KpMinus => self.set_speed_level(self.speed_level - 1),
+            Keycode::F if !repeat => self.toggle_fast_forward(),
+            Keycode::H if !repeat => self.hud_visible = !self.hud_visible,
+            Keycode::Num1 => self.select_tool(Tool::Spawn),
+            Keycode::Num2 => self.select_tool(Tool::Inspect),
+            Keycode::Num3 => self.select_tool(Tool::Wall),
+            Keycode::Num4 => self.select_tool(Tool::Food),
+            Keycode::Num5 => self.select_tool(Tool::Erase),
+            Keycode::Num6 => self.select_tool(Tool::Kill),
+            Keycode::LeftBracket => self.brush_size = (self.brush_size - 1).max(1),
+            Keycode::RightBracket => self.brush_size = (self.brush_size + 1).min(MAX_BRUSH_SIZE),
+            Keycode::C if !repeat => self.inspector.toggle_following(),
+            Keycode::Escape => self.inspector.clear(),
+            Keycode::F11 if !repeat => self.toggle_fullscreen()?,
+            Keycode::F12 if !repeat => self.take_screenshot()?,
+            Keycode::R if !repeat => self.toggle_recording()?,
+            Keycode::T if !repeat => self.cycle_theme()?,
+            Keycode::V if !repeat => self.cycle_heatmap_overlay(),
+            Keycode::X if !repeat => self.export_heatmap()?,
+            Keycode::Left
+            | Keycode::Right
+            | Keycode::Up
+            | Keycode::Down
+            | Keycode::W
+            | Keycode::A
+            | Keycode::S
+            | Keycode::D
+                if self.steer_player(keycode) => {}
+            Keycode::Return if !repeat => self.proceed_game()?,
+            Keycode::Left => self.pan_camera(-PAN_STEP, 0)?,
+            Keycode::Right => self.pan_camera(PAN_STEP, 0)?,
+            Keycode::Up => self.pan_camera(0, -PAN_STEP)?,
+            Keycode::Down => self.pan_camera(0, PAN_STEP)?,
+            Keycode::Home if !repeat => self.fit_camera()?,
+            _ => {}
         }
 
         Ok(())
     }
+
+    pub fn run(&mut self) -> Result<()> {
+        let mut event_pump = self.sdl_context.event_pump().map_err(Error::msg)?;
+
+        'running: loop {
+            for event in event_pump.poll_iter() {
+                if let Event::Quit { .. } = event {
+                    break 'running;
+                }
+
+                self.handle_event(&event)?;
+            }
+
+            self.frame()?;
+        }
+
+        self.stop_recording()
+    }
+}
+
+// Extends a segment's inset rectangle to the edge of its tile on `side`.
+fn bridge_rect(inset: Rect, tile: Rect, side: Direction) -> Rect {
+    match side {
+        Direction::Up => Rect::new(inset.x(), tile.y(), inset.width(), inset.height()),
+        Direction::Down => Rect::new(
+            inset.x(),
+            inset.y(),
+            inset.width(),
+            (tile.bottom() - inset.y()).unsigned_abs(),
+        ),
+        Direction::Left => Rect::new(tile.x(), inset.y(), inset.width(), inset.height()),
+        Direction::Right => Rect::new(
+            inset.x(),
+            inset.y(),
+            (tile.right() - inset.x()).unsigned_abs(),
+            inset.height(),
+        ),
+    }
+}
+
+// A small square just inside the edge of the head the wyrm is facing.
+fn head_marker_rect(head: Rect, direction: Direction) -> Rect {
+    let size = (head.width().min(head.height()) / 3).max(1);
+    let offset = size.cast_signed();
+    let center = head.center();
+    let (x, y) = match direction {
+        Direction::Up => (center.x() - offset / 2, head.y()),
+        Direction::Down => (center.x() - offset / 2, head.bottom() - offset),
+        Direction::Left => (head.x(), center.y() - offset / 2),
+        Direction::Right => (head.right() - offset, center.y() - offset / 2),
+    };
+    Rect::new(x, y, size, size)
 }
@@ -0,0 +1,39 @@
+use std::cmp::Reverse;
+
+use anyhow::Result;
+
+use crate::{RelativeDirection, SensorReading, World, Wyrm};
+
+pub trait Brain {
+    fn choose(&mut self, world: &World, wyrm: &Wyrm) -> Result<RelativeDirection>;
+
+    // Whether the wyrm splits into a child on reaching `split_length`.
+    fn can_reproduce(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct Forager;
+
+impl Brain for Forager {
+    fn choose(&mut self, world: &World, wyrm: &Wyrm) -> Result<RelativeDirection> {
+        let neighbors = world.get_neighbors(wyrm.head(), wyrm.direction)?;
+        let readings = world.sense(wyrm.id, world.config.sensor_range)?;
+
+        let (relative_direction, _) = neighbors
+            .into_iter()
+            .min_by_key(|&(relative_direction, score)| {
+                let reading = readings.iter().find(|r| r.direction == relative_direction);
+                let food_distance = reading
+                    .and_then(SensorReading::food_distance)
+                    .unwrap_or(u16::MAX);
+                let obstacle_distance = reading
+                    .and_then(SensorReading::obstacle_distance)
+                    .unwrap_or(u16::MAX);
+                (Reverse(score), food_distance, Reverse(obstacle_distance))
+            })
+            .expect("no neighbors");
+        Ok(relative_direction)
+    }
+}
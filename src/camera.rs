@@ -0,0 +1,97 @@
+use sdl2::rect::Rect;
+
+pub const MIN_ZOOM: f64 = 0.25;
+pub const MAX_ZOOM: f64 = 64.0;
+
+// Maps between world tile coordinates and output pixels. `zoom` is the size
+// of one tile in output pixels and `(x, y)` is the world position shown at the
+// center of the output.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    pub x: f64,
+    pub y: f64,
+    pub zoom: f64,
+}
+
+impl Camera {
+    #[must_use]
+    pub fn fit(world_size: (u16, u16), output_size: (u32, u32)) -> Self {
+        let (world_width, world_height) = (f64::from(world_size.0), f64::from(world_size.1));
+        let zoom_x = f64::from(output_size.0) / world_width;
+        let zoom_y = f64::from(output_size.1) / world_height;
+        Camera {
+            x: world_width / 2.0,
+            y: world_height / 2.0,
+            zoom: zoom_x.min(zoom_y),
+        }
+    }
+
+    #[must_use]
+    pub fn screen_to_world(&self, (sx, sy): (f64, f64), output_size: (u32, u32)) -> (f64, f64) {
+        let x = self.x + (sx - f64::from(output_size.0) / 2.0) / self.zoom;
+        let y = self.y + (sy - f64::from(output_size.1) / 2.0) / self.zoom;
+        (x, y)
+    }
+
+    #[must_use]
+    pub fn world_to_screen(&self, (wx, wy): (f64, f64), output_size: (u32, u32)) -> (f64, f64) {
+        let x = (wx - self.x) * self.zoom + f64::from(output_size.0) / 2.0;
+        let y = (wy - self.y) * self.zoom + f64::from(output_size.1) / 2.0;
+        (x, y)
+    }
+
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.x += dx / self.zoom;
+        self.y += dy / self.zoom;
+    }
+
+    // Zooms by `factor` while keeping the world position under `anchor` fixed.
+    pub fn zoom_at(&mut self, factor: f64, anchor: (f64, f64), output_size: (u32, u32)) {
+        let before = self.screen_to_world(anchor, output_size);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let after = self.screen_to_world(anchor, output_size);
+        self.x += before.0 - after.0;
+        self.y += before.1 - after.1;
+    }
+
+    pub fn clamp_to(&mut self, world_size: (u16, u16)) {
+        self.x = self.x.clamp(0.0, f64::from(world_size.0));
+        self.y = self.y.clamp(0.0, f64::from(world_size.1));
+    }
+
+    // Returns the visible part of the world in tiles, along with where it
+    // should be drawn in output pixels, or `None` if nothing is visible.
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    #[must_use]
+    pub fn visible_rects(
+        &self,
+        world_size: (u16, u16),
+        output_size: (u32, u32),
+    ) -> Option<(Rect, Rect)> {
+        let (left, top) = self.screen_to_world((0.0, 0.0), output_size);
+        let (right, bottom) = self.screen_to_world(
+            (f64::from(output_size.0), f64::from(output_size.1)),
+            output_size,
+        );
+
+        let x0 = left.floor().max(0.0);
+        let y0 = top.floor().max(0.0);
+        let x1 = right.ceil().min(f64::from(world_size.0));
+        let y1 = bottom.ceil().min(f64::from(world_size.1));
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+
+        let source = Rect::new(x0 as i32, y0 as i32, (x1 - x0) as u32, (y1 - y0) as u32);
+        let (sx0, sy0) = self.world_to_screen((x0, y0), output_size);
+        let (sx1, sy1) = self.world_to_screen((x1, y1), output_size);
+        let destination = Rect::new(
+            sx0.round() as i32,
+            sy0.round() as i32,
+            (sx1.round() - sx0.round()) as u32,
+            (sy1.round() - sy0.round()) as u32,
+        );
+        Some((source, destination))
+    }
+}
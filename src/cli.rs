@@ -0,0 +1,227 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    config::{Config, CONFIG_PATH},
+    GameMode, HeatmapKind, Map, VideoFormat, STDOUT_PATH,
+};
+
+pub const USAGE: &str = "\
+Usage: wymrs [OPTIONS]
+
+Options:
+  -c, --config <PATH>     Load settings from PATH [default: wymrs.conf]
+  -m, --map <PATH>        Load walls and food from a map file
+  -W, --width <TILES>     World width in tiles [default: window width / tile size]
+  -H, --height <TILES>    World height in tiles [default: window height / tile size]
+  -t, --tile-size <PX>    Size of each tile in pixels
+  -f, --fps <STEPS>       Simulation steps per second
+  -s, --seed <SEED>       Seed for the random number generator
+  -n, --steps <COUNT>     Run COUNT steps without a window, then exit
+  -o, --output <PATH>     Write the final map to PATH (requires --steps)
+      --screenshot <PATH> Save a PNG or PPM image of the final world to PATH
+                          (requires --steps)
+      --text <PATH>       Write the final world as text, one character per
+                          tile, to PATH, or to stdout if PATH is `-`
+                          (requires --steps)
+      --gif <PATH>        Record the run as an animated GIF to PATH
+                          (requires --steps)
+      --video <PATH>      Stream every frame as video to PATH, or to stdout
+                          if PATH is `-` (requires --steps)
+      --video-format <FORMAT>
+                          `y4m` (YUV4MPEG2) or `ppm` (PPM images back to
+                          back) [default: y4m]
+      --heatmap <PATH>    Save the run's heatmaps to PATH: every counter as CSV
+                          if PATH ends in `.csv`, otherwise one of them as a
+                          PNG or PPM image (requires --steps)
+      --heatmap-kind <KIND>
+                          `visits`, `deaths` or `food` [default: visits]
+  -T, --terminal          Run in the terminal instead of opening a window
+                          (Unix-like systems only)
+  -g, --game <MODE>       `snake` to steer a wyrm with the arrow keys,
+                          `versus` for two players on WASD and the arrow
+                          keys, or `off` [default: from the config file]
+  -h, --help              Print this help and exit
+
+Exit codes:
+  0  success
+  1  runtime error
+  2  invalid arguments, config file or map file
+";
+
+pub enum Command {
+    Help,
+    Run(Box<Args>),
+}
+
+#[derive(Default)]
+pub struct Args {
+    pub config_path: Option<PathBuf>,
+    pub map_path: Option<PathBuf>,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub tile_size: Option<u32>,
+    pub fps: Option<u64>,
+    pub seed: Option<u64>,
+    pub steps: Option<usize>,
+    pub output_path: Option<PathBuf>,
+    pub screenshot_path: Option<PathBuf>,
+    pub text_path: Option<PathBuf>,
+    pub gif_path: Option<PathBuf>,
+    pub video_path: Option<PathBuf>,
+    pub video_format: VideoFormat,
+    pub heatmap_path: Option<PathBuf>,
+    pub heatmap_kind: HeatmapKind,
+    pub terminal: bool,
+    pub game: Option<GameMode>,
+}
+
+fn parse_flag_value<T>(flag: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| anyhow!("invalid value for `{flag}`: {value:?} ({err})"))
+}
+
+impl Command {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, mut inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("`{flag}` requires a value"))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-c" | "--config" => parsed.config_path = Some(value()?.into()),
+                "-m" | "--map" => parsed.map_path = Some(value()?.into()),
+                "-W" | "--width" => parsed.width = Some(parse_flag_value(&flag, &value()?)?),
+                "-H" | "--height" => parsed.height = Some(parse_flag_value(&flag, &value()?)?),
+                "-t" | "--tile-size" => {
+                    parsed.tile_size = Some(parse_flag_value(&flag, &value()?)?);
+                }
+                "-f" | "--fps" => parsed.fps = Some(parse_flag_value(&flag, &value()?)?),
+                "-s" | "--seed" => parsed.seed = Some(parse_flag_value(&flag, &value()?)?),
+                "-n" | "--steps" => parsed.steps = Some(parse_flag_value(&flag, &value()?)?),
+                "-o" | "--output" => parsed.output_path = Some(value()?.into()),
+                "--screenshot" => parsed.screenshot_path = Some(value()?.into()),
+                "-T" | "--terminal" if cfg!(unix) => parsed.terminal = true,
+                "-T" | "--terminal" => bail!("`{flag}` is only supported on Unix-like systems"),
+                "--text" => parsed.text_path = Some(value()?.into()),
+                "--gif" => parsed.gif_path = Some(value()?.into()),
+                "--video" => parsed.video_path = Some(value()?.into()),
+                "--video-format" => {
+                    parsed.video_format = parse_flag_value(&flag, &value()?)?;
+                }
+                "-g" | "--game" => parsed.game = Some(parse_flag_value(&flag, &value()?)?),
+                "--heatmap" => parsed.heatmap_path = Some(value()?.into()),
+                "--heatmap-kind" => {
+                    parsed.heatmap_kind = parse_flag_value(&flag, &value()?)?;
+                }
+                _ if flag.starts_with('-') => bail!("unknown option `{flag}`"),
+                _ => bail!("unexpected argument {flag:?}"),
+            }
+        }
+
+        if parsed.output_path.is_some() && parsed.steps.is_none() {
+            bail!("`--output` requires `--steps`");
+        }
+        if parsed.screenshot_path.is_some() && parsed.steps.is_none() {
+            bail!("`--screenshot` requires `--steps`");
+        }
+        if parsed.text_path.is_some() && parsed.steps.is_none() {
+            bail!("`--text` requires `--steps`");
+        }
+        let stdout = Some(Path::new(STDOUT_PATH));
+        if parsed.text_path.as_deref() == stdout && parsed.video_path.as_deref() == stdout {
+            bail!("`--text` and `--video` can't both write to stdout");
+        }
+        if parsed.gif_path.is_some() && parsed.steps.is_none() {
+            bail!("`--gif` requires `--steps`");
+        }
+        if parsed.video_path.is_some() && parsed.steps.is_none() {
+            bail!("`--video` requires `--steps`");
+        }
+        if parsed.heatmap_path.is_some() && parsed.steps.is_none() {
+            bail!("`--heatmap` requires `--steps`");
+        }
+        if parsed.terminal && parsed.steps.is_some() {
+            bail!("`--terminal` can't be combined with `--steps`");
+        }
+        if parsed.game.is_some() && (parsed.steps.is_some() || parsed.terminal) {
+            bail!("`--game` needs a window and can't be combined with `--steps` or `--terminal`");
+        }
+
+        Ok(Command::Run(Box::new(parsed)))
+    }
+}
+
+impl Args {
+    pub fn config(&self) -> Result<Config> {
+        let mut config = match &self.config_path {
+            Some(path) => Config::load(path)?,
+            None => Config::load_or_default(Path::new(CONFIG_PATH))?,
+        };
+
+        if let Some(width) = self.width {
+            config.world_width = Some(width);
+        }
+        if let Some(height) = self.height {
+            config.world_height = Some(height);
+        }
+        if let Some(tile_size) = self.tile_size {
+            config.tile_size = tile_size;
+        }
+        if let Some(fps) = self.fps {
+            config.fps = fps;
+        }
+        if let Some(seed) = self.seed {
+            config.world.seed = Some(seed);
+        }
+        if let Some(game) = self.game {
+            config.game = game;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn map(&self) -> Result<Option<Map>> {
+        let Some(path) = &self.map_path else {
+            return Ok(None);
+        };
+
+        let map = Map::load(path)?;
+        if self.width.is_some_and(|width| width != map.width)
+            || self.height.is_some_and(|height| height != map.height)
+        {
+            bail!(
+                "world size conflicts with map {} ({}x{})",
+                path.display(),
+                map.width,
+                map.height
+            );
+        }
+
+        Ok(Some(map))
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+// A fixed-timestep clock: elapsed wall time is accumulated and converted into
+// a whole number of simulation steps, independently of how often frames are
+// rendered. At most `max_steps_per_tick` steps are produced per tick so a slow
+// frame doesn't snowball into ever longer catch-up bursts.
+pub struct SimulationClock {
+    step_time: Duration,
+    max_steps_per_tick: u32,
+    accumulator: Duration,
+    last_tick: Option<Instant>,
+    stats_start: Instant,
+    stats_steps: u32,
+    stats_frames: u32,
+    steps_per_second: f64,
+    frame_time: Duration,
+}
+
+impl SimulationClock {
+    #[must_use]
+    pub fn new(step_time: Duration, max_steps_per_tick: u32) -> Self {
+        SimulationClock {
+            step_time,
+            max_steps_per_tick,
+            accumulator: Duration::ZERO,
+            last_tick: None,
+            stats_start: Instant::now(),
+            stats_steps: 0,
+            stats_frames: 0,
+            steps_per_second: 0.0,
+            frame_time: Duration::ZERO,
+        }
+    }
+
+    #[must_use]
+    pub fn step_time(&self) -> Duration {
+        self.step_time
+    }
+
+    pub fn set_step_time(&mut self, step_time: Duration) {
+        self.step_time = step_time;
+        self.accumulator = self.accumulator.min(step_time);
+    }
+
+    // Forgets any accumulated time, e.g. after the simulation was paused.
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.last_tick = None;
+    }
+
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = self.last_tick.map_or(self.step_time, |last| now - last);
+        self.last_tick = Some(now);
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step_time && steps < self.max_steps_per_tick {
+            self.accumulator -= self.step_time;
+            steps += 1;
+        }
+
+        if steps == self.max_steps_per_tick {
+            self.accumulator = self.accumulator.min(self.step_time);
+        }
+
+        steps
+    }
+
+    // How far the simulation is between the last step and the next one, from
+    // 0 to 1, for interpolating animation.
+    #[must_use]
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator.as_secs_f64() / self.step_time.as_secs_f64()).min(1.0)
+    }
+
+    pub fn record_steps(&mut self, steps: u32) {
+        self.stats_steps += steps;
+    }
+
+    // Returns true when the measured rates were updated.
+    pub fn record_frame(&mut self) -> bool {
+        self.stats_frames += 1;
+        let elapsed = self.stats_start.elapsed();
+        if elapsed < STATS_INTERVAL {
+            return false;
+        }
+
+        self.steps_per_second = f64::from(self.stats_steps) / elapsed.as_secs_f64();
+        self.frame_time = elapsed / self.stats_frames;
+        self.stats_start = Instant::now();
+        self.stats_steps = 0;
+        self.stats_frames = 0;
+        true
+    }
+
+    #[must_use]
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps_per_second
+    }
+
+    #[must_use]
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP_TIME: Duration = Duration::from_secs(1);
+
+    fn clock_behind_by(steps: u32, max_steps_per_tick: u32) -> SimulationClock {
+        let mut clock = SimulationClock::new(STEP_TIME, max_steps_per_tick);
+        clock.last_tick = Instant::now().checked_sub(STEP_TIME * steps);
+        clock
+    }
+
+    #[test]
+    fn first_tick_runs_one_step() {
+        let mut clock = SimulationClock::new(STEP_TIME, 5);
+        assert_eq!(clock.tick(), 1);
+        assert!(clock.alpha() < 0.5);
+    }
+
+    #[test]
+    fn catches_up_on_missed_steps() {
+        let mut clock = clock_behind_by(3, 5);
+        assert_eq!(clock.tick(), 3);
+        assert_eq!(clock.tick(), 0);
+    }
+
+    #[test]
+    fn caps_catch_up_and_drops_the_backlog() {
+        let mut clock = clock_behind_by(10, 3);
+        assert_eq!(clock.tick(), 3);
+        // At most one step is carried over instead of the other seven.
+        assert_eq!(clock.tick(), 1);
+        assert_eq!(clock.tick(), 0);
+    }
+
+    #[test]
+    fn reset_forgets_accumulated_time() {
+        let mut clock = clock_behind_by(10, 3);
+        clock.tick();
+        clock.reset();
+        assert!(clock.alpha() < f64::EPSILON);
+        assert_eq!(clock.tick(), 1);
+    }
+}
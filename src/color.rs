@@ -1,83 +1,187 @@
 use anyhow::Result;
-use once_cell::sync::Lazy;
 use palette::{FromColor, Oklch, Srgb};
 use rand::Rng;
 use rand_distr::Normal;
 pub use sdl2::pixels::Color;
 
-use crate::tile;
-
-type NormalDistributionValues = (f32, f32);
-type LchDistributionValues = (
+pub type NormalDistributionValues = (f32, f32);
+pub type LchDistributionValues = (
     NormalDistributionValues,
     NormalDistributionValues,
     NormalDistributionValues,
 );
 
-pub static EMPTY: Lazy<Color> = Lazy::new(|| color_from_oklch(0.18, 0.05, 250.0));
-pub static WALL: Lazy<Color> = Lazy::new(|| color_from_oklch(0.6, 0.09, 300.0));
-pub static FOOD: Lazy<Color> = Lazy::new(|| color_from_oklch(0.4, 0.15, 20.0));
-pub static MISSING: Lazy<Color> = Lazy::new(|| color_from_oklch(1.0, 0.0, 0.0));
-
-// common
-const ORANGEYELLOW: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (90.0, 10.0));
-const YELLOWGREEN: LchDistributionValues = ((0.85, 0.05), (0.3, 0.05), (120.0, 5.0));
-const GREENGREEN: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (145.0, 5.0));
-const BLUEGREEN: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (160.0, 5.0));
-const CREAM: LchDistributionValues = ((0.85, 0.05), (0.1, 0.05), (80.0, 10.0));
-
-// exotic
-const LIGHTBLUE: LchDistributionValues = ((0.85, 0.05), (0.2, 0.05), (220.0, 5.0));
-const FUCHSIA: LchDistributionValues = ((0.67, 0.05), (0.3, 0.02), (10.0, 10.0));
-
-const WYRM_COLOR_DISTRIBUTIONS: [LchDistributionValues; 15] = [
-    ORANGEYELLOW,
-    ORANGEYELLOW,
-    YELLOWGREEN,
-    YELLOWGREEN,
-    YELLOWGREEN,
-    GREENGREEN,
-    GREENGREEN,
-    GREENGREEN,
-    BLUEGREEN,
-    BLUEGREEN,
-    BLUEGREEN,
-    CREAM,
-    CREAM,
-    LIGHTBLUE,
-    FUCHSIA,
-];
+// How far a lineage's color has drifted from its founder's, as offsets to
+// Oklch lightness, chroma and hue.
+pub type ColorDrift = (f32, f32, f32);
 
 const HUE_MAX: f32 = 360.0;
 
+// Degrees of hue mutation per unit of lightness mutation, so both drift about
+// equally visibly.
+const HUE_MUTATION_SCALE: f32 = 200.0;
+
+// Oklch lightness added to a wyrm's head and the factor its tail is dimmed by.
+const HEAD_BRIGHTENING: f32 = 0.12;
+const TAIL_DIMMING: f32 = 0.55;
+// Shades from head to tail. Moving a wyrm only changes the shade of the few
+// segments that cross from one level into the next.
+pub const SHADE_LEVELS: usize = 8;
+
 fn random_normal<R: Rng>(rng: &mut R, (mean, std_dev): NormalDistributionValues) -> Result<f32> {
     let distribution = Normal::new(mean, std_dev)?;
     Ok(rng.sample(distribution))
 }
 
-fn color_from_oklch(l: f32, chroma: f32, hue: f32) -> Color {
+#[must_use]
+pub fn color_from_oklch(l: f32, chroma: f32, hue: f32) -> Color {
     let lch = Oklch::new(l, chroma, hue);
     let rgb: Srgb<u8> = Srgb::from_color(lch).into_format();
     let (r, g, b) = rgb.into_components();
     Color::RGB(r, g, b)
 }
 
-fn random_color<R: Rng>(rng: &mut R, (dl, dc, dh): LchDistributionValues) -> Result<Color> {
+#[must_use]
+pub fn oklch_from_color(color: Color) -> Oklch {
+    let rgb: Srgb<f32> = Srgb::new(color.r, color.g, color.b).into_format();
+    Oklch::from_color(rgb)
+}
+
+// Parses colors written like `#1a2b3c`.
+#[must_use]
+pub fn color_from_hex(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Some(Color::RGB(r, g, b))
+}
+
+pub fn random_color<R: Rng>(rng: &mut R, (dl, dc, dh): LchDistributionValues) -> Result<Color> {
     let l = random_normal(rng, dl)?;
     let chroma = random_normal(rng, dc)?;
     let hue = (random_normal(rng, dh)? + HUE_MAX) % HUE_MAX;
     Ok(color_from_oklch(l, chroma, hue))
 }
 
-#[allow(clippy::module_name_repetitions)]
-pub fn random_wyrm_color<R: Rng>(rng: &mut R, id: u16) -> Result<Color> {
-    let n: i32 = WYRM_COLOR_DISTRIBUTIONS.len().try_into()?;
+// One generation's worth of random color drift; `amount` is the standard
+// deviation of the lightness change.
+pub fn random_mutation<R: Rng>(rng: &mut R, amount: f32) -> Result<ColorDrift> {
+    Ok((
+        random_normal(rng, (0.0, amount))?,
+        random_normal(rng, (0.0, amount / 2.0))?,
+        random_normal(rng, (0.0, amount * HUE_MUTATION_SCALE))?,
+    ))
+}
+
+#[must_use]
+pub fn drift_color(color: Color, (l, chroma, hue): ColorDrift) -> Color {
+    let lch = oklch_from_color(color);
+    color_from_oklch(
+        (lch.l + l).clamp(0.0, 1.0),
+        (lch.chroma + chroma).max(0.0),
+        (lch.hue.into_degrees() + hue).rem_euclid(HUE_MAX),
+    )
+}
+
+// Which of the `SHADE_LEVELS` the segment at `index` (0 being the head) of a
+// wyrm with `length` segments is drawn with, from 0 at the head to the last
+// level at the tail.
+#[must_use]
+pub fn shade_level(index: usize, length: usize) -> usize {
+    if length > 1 {
+        (index * (SHADE_LEVELS - 1) + (length - 1) / 2) / (length - 1)
+    } else {
+        0
+    }
+}
+
+// Shades the segment at `index` of a wyrm with `length` segments: the head is
+// brighter than `color` and the body fades towards the tail in Oklch
+// lightness, keeping chroma and hue.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn shade_segment(color: Color, index: usize, length: usize) -> Color {
+    let lch = oklch_from_color(color);
+    let t = shade_level(index, length) as f32 / (SHADE_LEVELS - 1) as f32;
+    let head = (lch.l + HEAD_BRIGHTENING).min(1.0);
+    let tail = lch.l * TAIL_DIMMING;
+    color_from_oklch(head + (tail - head) * t, lch.chroma, lch.hue.into_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const ORANGE: Color = Color::RGB(230, 120, 30);
+
+    fn std_dev(values: impl Iterator<Item = f32>) -> f32 {
+        let values: Vec<f32> = values.collect();
+        let count = f32::from(u16::try_from(values.len()).unwrap());
+        (values.iter().map(|value| value * value).sum::<f32>() / count).sqrt()
+    }
+
+    fn assert_near(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn mutates_chroma_and_hue_in_proportion_to_lightness() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let amount = 0.02;
+        let mutations: Vec<ColorDrift> = (0..2000)
+            .map(|_| random_mutation(&mut rng, amount).unwrap())
+            .collect();
+        assert_near(
+            std_dev(mutations.iter().map(|m| m.0)),
+            amount,
+            amount / 10.0,
+        );
+        assert_near(
+            std_dev(mutations.iter().map(|m| m.1)),
+            amount / 2.0,
+            amount / 20.0,
+        );
+        assert_near(
+            std_dev(mutations.iter().map(|m| m.2)),
+            amount * HUE_MUTATION_SCALE,
+            amount * HUE_MUTATION_SCALE / 10.0,
+        );
+
+        assert_eq!(random_mutation(&mut rng, 0.0).unwrap(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn clamps_lightness_and_chroma() {
+        // Lightness stops at 0 and 1, however far it drifts.
+        let light = drift_color(ORANGE, (2.0, 0.0, 0.0));
+        let dark = drift_color(ORANGE, (-2.0, 0.0, 0.0));
+        assert_eq!(light, drift_color(ORANGE, (5.0, 0.0, 0.0)));
+        assert_eq!(dark, drift_color(ORANGE, (-5.0, 0.0, 0.0)));
+        assert!(oklch_from_color(light).l > oklch_from_color(ORANGE).l);
+        assert!(oklch_from_color(dark).l < 0.1);
 
-    let offset: i32 = rng.gen_range(-1..=3);
-    let base_index = i32::from(id - tile::WYRM);
-    let raw_index = (base_index + offset + n) % n;
-    let index = usize::try_from(raw_index)?;
+        let gray = drift_color(ORANGE, (0.0, -1.0, 0.0));
+        assert!(gray.r.abs_diff(gray.g) <= 1 && gray.g.abs_diff(gray.b) <= 1);
+        assert_near(oklch_from_color(gray).l, oklch_from_color(ORANGE).l, 0.01);
+    }
 
-    let dist_vals = WYRM_COLOR_DISTRIBUTIONS[index];
-    random_color(rng, dist_vals)
+    #[test]
+    fn wraps_hue() {
+        let unchanged = drift_color(ORANGE, (0.0, 0.0, 0.0));
+        assert_eq!(drift_color(ORANGE, (0.0, 0.0, HUE_MAX)), unchanged);
+        assert_eq!(
+            drift_color(ORANGE, (0.0, 0.0, -90.0)),
+            drift_color(ORANGE, (0.0, 0.0, 270.0))
+        );
+        assert_ne!(drift_color(ORANGE, (0.0, 0.0, 90.0)), unchanged);
+    }
 }
@@ -1,16 +1,509 @@
-use std::time::Duration;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
+use anyhow::{anyhow, bail, Context, Result};
 use sdl2::pixels::{Color, PixelFormatEnum};
 
-pub const WINDOW_TITLE: &str = "wyrms";
-pub const WINDOW_WIDTH: u32 = 1024;
-pub const WINDOW_HEIGHT: u32 = 768;
+use crate::{
+    color, GameMode, HudItem, ImageFormat, ScreenshotScale, Theme, TileStorage, VersusRule,
+};
 
-pub const TILE_SIZE: u32 = 8;
-pub const SPAWN_INTERVAL: usize = 32;
+pub const CONFIG_PATH: &str = "wymrs.conf";
+pub const PIXEL_FORMAT: PixelFormatEnum = PixelFormatEnum::RGB24;
+// 48 MB of RGB.
+pub const MAX_SCREENSHOT_PIXELS: u64 = 1 << 24;
 
-pub const FPS: u64 = 16;
-pub const STEP_TIME: Duration = Duration::from_nanos(1_000_000_000 / FPS);
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ResizePolicy {
+    Rescale,
+    Resize,
+}
 
-pub const CLEAR_COLOR: Color = Color::BLACK;
-pub const PIXEL_FORMAT: PixelFormatEnum = PixelFormatEnum::RGB24;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WyrmStyle {
+    Flat,
+    Shaded,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub window_title: String,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub world_width: Option<u16>,
+    pub world_height: Option<u16>,
+    pub tile_size: u32,
+    pub fps: u64,
+    pub max_catch_up_steps: u32,
+    pub clear_color: Color,
+    pub resize_policy: ResizePolicy,
+    pub fullscreen: bool,
+    pub hud: bool,
+    pub hud_scale: u32,
+    pub hud_items: Vec<HudItem>,
+    pub screenshot_dir: PathBuf,
+    pub screenshot_format: ImageFormat,
+    pub screenshot_scale: ScreenshotScale,
+    pub recording_dir: PathBuf,
+    pub recording_scale: ScreenshotScale,
+    pub recording_frame_skip: u32,
+    pub game: GameMode,
+    pub player_lives: u32,
+    pub versus_rule: VersusRule,
+    pub versus_target_length: usize,
+    // Round wins needed to take a versus match.
+    pub versus_rounds: u32,
+    pub world: WorldConfig,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Debug)]
+pub struct WorldConfig {
+    pub spawn_interval: usize,
+    pub spawn_mean: f32,
+    pub spawn_std_dev: f32,
+    pub food_density: f64,
+    pub poop_chance: f64,
+    pub sensor_range: u16,
+    pub pathfinder_budget: usize,
+    pub seed: Option<u64>,
+    pub tile_storage: TileStorage,
+    pub unbounded: bool,
+    pub wyrm_style: WyrmStyle,
+    pub theme: Theme,
+    // Wyrms split in two on reaching this length; 0, the default, disables
+    // reproduction.
+    pub split_length: usize,
+    // Standard deviation of the Oklch lightness change between a parent's
+    // color and its child's.
+    pub color_mutation: f32,
+    // Whether to count visits, deaths and food per tile for the heatmaps.
+    pub heatmaps: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            window_title: "wyrms".to_string(),
+            window_width: 1024,
+            window_height: 768,
+            world_width: None,
+            world_height: None,
+            tile_size: 8,
+            fps: 16,
+            max_catch_up_steps: 8,
+            clear_color: Color::BLACK,
+            resize_policy: ResizePolicy::Rescale,
+            fullscreen: false,
+            hud: true,
+            hud_scale: 2,
+            hud_items: vec![
+                HudItem::Step,
+                HudItem::Population,
+                HudItem::Speed,
+                HudItem::StepsPerSecond,
+                HudItem::FramesPerSecond,
+                HudItem::Tool,
+            ],
+            screenshot_dir: PathBuf::from("."),
+            screenshot_format: ImageFormat::Png,
+            screenshot_scale: ScreenshotScale::TileSize,
+            recording_dir: PathBuf::from("."),
+            recording_scale: ScreenshotScale::Tile,
+            recording_frame_skip: 0,
+            game: GameMode::Off,
+            player_lives: 3,
+            versus_rule: VersusRule::Length,
+            versus_target_length: 16,
+            versus_rounds: 3,
+            world: WorldConfig::default(),
+        }
+    }
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            spawn_interval: 32,
+            spawn_mean: 0.5,
+            spawn_std_dev: 0.1,
+            food_density: 1.0 / 16.0,
+            poop_chance: 1.0 / 32.0,
+            sensor_range: 16,
+            pathfinder_budget: 4096,
+            seed: None,
+            tile_storage: TileStorage::Dense,
+            unbounded: false,
+            wyrm_style: WyrmStyle::Shaded,
+            theme: Theme::default(),
+            split_length: 0,
+            color_mutation: 0.03,
+            heatmaps: true,
+        }
+    }
+}
+
+pub fn parse_value<T>(key: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| anyhow!("invalid value for `{key}`: {value:?} ({err})"))
+}
+
+pub fn parse_color(key: &str, value: &str) -> Result<Color> {
+    color::color_from_hex(value).ok_or_else(|| {
+        anyhow!("invalid value for `{key}`: {value:?} (expected a color like #1a2b3c)")
+    })
+}
+
+impl FromStr for TileStorage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dense" => Ok(TileStorage::Dense),
+            "chunked" => Ok(TileStorage::Chunked),
+            _ => bail!("expected `dense` or `chunked`"),
+        }
+    }
+}
+
+impl FromStr for ResizePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rescale" => Ok(ResizePolicy::Rescale),
+            "resize" => Ok(ResizePolicy::Resize),
+            _ => bail!("expected `rescale` or `resize`"),
+        }
+    }
+}
+
+impl FromStr for WyrmStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "flat" => Ok(WyrmStyle::Flat),
+            "shaded" => Ok(WyrmStyle::Shaded),
+            _ => bail!("expected `flat` or `shaded`"),
+        }
+    }
+}
+
+fn parse_list<T>(key: &str, value: &str) -> Result<Vec<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| parse_value(key, item))
+        .collect()
+}
+
+#[must_use]
+pub fn parse_string(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+// Values such as colors can start with `#`, so a trailing comment has to be
+// separated from the value by whitespace. Quoted strings may contain `#`.
+fn strip_comment(value: &str) -> &str {
+    let value = value.trim();
+    let start = match value.strip_prefix('"') {
+        Some(quoted) => quoted.find('"').map_or(value.len(), |end| end + 2),
+        None => 0,
+    };
+
+    let comment = value[start..]
+        .match_indices('#')
+        .map(|(i, _)| start + i)
+        .find(|&i| value[..i].ends_with(char::is_whitespace));
+    match comment {
+        Some(i) => value[..i].trim_end(),
+        None => value,
+    }
+}
+
+// Calls `set` with the key and value of every `key = value` line, skipping
+// blank lines and comments. Errors say which line they're on. Theme files
+// share this format.
+pub fn parse_lines(source: &str, mut set: impl FnMut(&str, &str) -> Result<()>) -> Result<()> {
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_number = i + 1;
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {line_number}: expected `key = value`"))?;
+        set(key.trim(), strip_comment(value)).with_context(|| format!("line {line_number}"))?;
+    }
+
+    Ok(())
+}
+
+fn ensure_positive<T: Copy + Default + PartialOrd>(key: &str, value: T) -> Result<()> {
+    if value > T::default() {
+        Ok(())
+    } else {
+        bail!("`{key}` must be greater than 0")
+    }
+}
+
+fn ensure_probability<T: Into<f64>>(key: &str, value: T) -> Result<()> {
+    if (0.0..=1.0).contains(&value.into()) {
+        Ok(())
+    } else {
+        bail!("`{key}` must be between 0 and 1")
+    }
+}
+
+impl Config {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut config = Config::default();
+        parse_lines(source, |key, value| config.set(key, value))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        Config::parse(&source).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Config::load(path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "window_title" => self.window_title = parse_string(value),
+            "window_width" => self.window_width = parse_value(key, value)?,
+            "window_height" => self.window_height = parse_value(key, value)?,
+            "world_width" => self.world_width = Some(parse_value(key, value)?),
+            "world_height" => self.world_height = Some(parse_value(key, value)?),
+            "tile_size" => self.tile_size = parse_value(key, value)?,
+            "fps" => self.fps = parse_value(key, value)?,
+            "max_catch_up_steps" => self.max_catch_up_steps = parse_value(key, value)?,
+            "clear_color" => self.clear_color = parse_color(key, value)?,
+            "resize_policy" => self.resize_policy = parse_value(key, value)?,
+            "fullscreen" => self.fullscreen = parse_value(key, value)?,
+            "hud" => self.hud = parse_value(key, value)?,
+            "hud_scale" => self.hud_scale = parse_value(key, value)?,
+            "hud_items" => self.hud_items = parse_list(key, value)?,
+            "screenshot_dir" => self.screenshot_dir = parse_string(value).into(),
+            "screenshot_format" => self.screenshot_format = parse_value(key, value)?,
+            "screenshot_scale" => self.screenshot_scale = parse_value(key, value)?,
+            "recording_dir" => self.recording_dir = parse_string(value).into(),
+            "recording_scale" => self.recording_scale = parse_value(key, value)?,
+            "recording_frame_skip" => self.recording_frame_skip = parse_value(key, value)?,
+            "game" => self.game = parse_value(key, value)?,
+            "player_lives" => self.player_lives = parse_value(key, value)?,
+            "versus_rule" => self.versus_rule = parse_value(key, value)?,
+            "versus_target_length" => self.versus_target_length = parse_value(key, value)?,
+            "versus_rounds" => self.versus_rounds = parse_value(key, value)?,
+            "spawn_interval" => self.world.spawn_interval = parse_value(key, value)?,
+            "spawn_mean" => self.world.spawn_mean = parse_value(key, value)?,
+            "spawn_std_dev" => self.world.spawn_std_dev = parse_value(key, value)?,
+            "food_density" => self.world.food_density = parse_value(key, value)?,
+            "poop_chance" => self.world.poop_chance = parse_value(key, value)?,
+            "sensor_range" => self.world.sensor_range = parse_value(key, value)?,
+            "pathfinder_budget" => self.world.pathfinder_budget = parse_value(key, value)?,
+            "seed" => self.world.seed = Some(parse_value(key, value)?),
+            "tile_storage" => self.world.tile_storage = parse_value(key, value)?,
+            "unbounded" => self.world.unbounded = parse_value(key, value)?,
+            "wyrm_style" => self.world.wyrm_style = parse_value(key, value)?,
+            "theme" => self.world.theme = Theme::from_setting(&parse_string(value))?,
+            "split_length" => self.world.split_length = parse_value(key, value)?,
+            "color_mutation" => self.world.color_mutation = parse_value(key, value)?,
+            "heatmaps" => self.world.heatmaps = parse_value(key, value)?,
+            _ => bail!("unknown key `{key}`"),
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure_positive("window_width", self.window_width)?;
+        ensure_positive("window_height", self.window_height)?;
+        ensure_positive("tile_size", self.tile_size)?;
+        ensure_positive("fps", self.fps)?;
+        ensure_positive("max_catch_up_steps", self.max_catch_up_steps)?;
+        ensure_positive("hud_scale", self.hud_scale)?;
+        ensure_positive("player_lives", self.player_lives)?;
+        ensure_positive("versus_rounds", self.versus_rounds)?;
+        if self.versus_target_length < 2 {
+            bail!("`versus_target_length` must be at least 2");
+        }
+        if self.tile_size > self.window_width || self.tile_size > self.window_height {
+            bail!("`tile_size` must not exceed `window_width` or `window_height`");
+        }
+        // Sizes derived from the window can be too small as well, e.g. with a
+        // huge `tile_size`.
+        let (width, height) = self.world_size().context("the world is too large")?;
+        for (key, size, derived_from) in [
+            ("world_width", width, "window_width"),
+            ("world_height", height, "window_height"),
+        ] {
+            if size < 3 {
+                bail!("`{key}` must be at least 3, but `{derived_from} / tile_size` gives {size}");
+            }
+        }
+
+        self.world.validate()
+    }
+
+    pub fn world_size(&self) -> Result<(u16, u16)> {
+        let width = match self.world_width {
+            Some(width) => width,
+            None => u16::try_from(self.window_width / self.tile_size)?,
+        };
+        let height = match self.world_height {
+            Some(height) => height,
+            None => u16::try_from(self.window_height / self.tile_size)?,
+        };
+        Ok((width, height))
+    }
+
+    // Screenshots of huge worlds fall back to fewer pixels per tile, down to
+    // one, to stay within `MAX_SCREENSHOT_PIXELS`.
+    #[must_use]
+    pub fn screenshot_scale_factor(&self, (width, height): (u16, u16)) -> u32 {
+        let tiles = u64::from(width) * u64::from(height);
+        let mut scale = self.screenshot_scale.factor(self.tile_size).max(1);
+        while scale > 1 && tiles * u64::from(scale).pow(2) > MAX_SCREENSHOT_PIXELS {
+            scale -= 1;
+        }
+        scale
+    }
+
+    #[must_use]
+    pub fn recording_scale_factor(&self) -> u32 {
+        self.recording_scale.factor(self.tile_size)
+    }
+
+    #[must_use]
+    pub fn step_time(&self) -> Duration {
+        Duration::from_nanos(1_000_000_000 / self.fps)
+    }
+}
+
+impl WorldConfig {
+    pub fn validate(&self) -> Result<()> {
+        ensure_positive("spawn_interval", self.spawn_interval)?;
+        ensure_probability("spawn_mean", self.spawn_mean)?;
+        ensure_positive("spawn_std_dev", self.spawn_std_dev)?;
+        ensure_probability("food_density", self.food_density)?;
+        ensure_probability("poop_chance", self.poop_chance)?;
+        ensure_positive("sensor_range", self.sensor_range)?;
+        ensure_positive("pathfinder_budget", self.pathfinder_budget)?;
+        if self.unbounded && self.tile_storage != TileStorage::Chunked {
+            bail!("`unbounded` requires `tile_storage = chunked`");
+        }
+        if (1..4).contains(&self.split_length) {
+            bail!("`split_length` must be 0 or at least 4");
+        }
+        ensure_probability("color_mutation", self.color_mutation)?;
+        self.theme
+            .validate()
+            .with_context(|| format!("invalid theme `{}`", self.theme.name))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first code block after the README's configuration heading.
+    fn readme_example() -> &'static str {
+        let readme = include_str!("../README.md");
+        let section = &readme[readme.find("## Configuration").unwrap()..];
+        let start = section.find("```\n").unwrap() + 4;
+        let end = start + section[start..].find("```").unwrap();
+        &section[start..end]
+    }
+
+    #[test]
+    fn parses_readme_example() {
+        let config = Config::parse(readme_example()).unwrap();
+        let default = Config::default();
+        assert_eq!(config.window_title, default.window_title);
+        assert_eq!(config.clear_color, Color::BLACK);
+        assert_eq!(config.resize_policy, ResizePolicy::Rescale);
+        assert_eq!(config.world.tile_storage, TileStorage::Dense);
+        assert_eq!(config.world.split_length, default.world.split_length);
+        assert_eq!(config.world_width, None);
+    }
+
+    #[test]
+    fn keeps_hash_inside_values() {
+        let config = Config::parse(
+            "clear_color = #1a2b3c # dark blue\n\
+             window_title = \"wyrms # 2\"   # quoted\n\
+             screenshot_dir = shots#1\n",
+        )
+        .unwrap();
+        assert_eq!(config.clear_color, Color::RGB(0x1a, 0x2b, 0x3c));
+        assert_eq!(config.window_title, "wyrms # 2");
+        assert_eq!(config.screenshot_dir, PathBuf::from("shots#1"));
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let err = Config::parse("fps = 16\n\nfps = fast\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3");
+
+        let err = Config::parse("# comment\nbogus = 1\n").unwrap_err();
+        assert_eq!(format!("{err:#}"), "line 2: unknown key `bogus`");
+    }
+
+    #[test]
+    fn validates_values() {
+        assert!(Config::parse("tile_size = 0").is_err());
+        assert!(Config::parse("food_density = 2").is_err());
+        assert!(Config::parse("split_length = 3").is_err());
+        assert!(Config::parse("unbounded = true").is_err());
+        assert!(Config::parse("unbounded = true\ntile_storage = chunked").is_ok());
+    }
+
+    #[test]
+    fn validates_world_size() {
+        assert!(Config::parse("world_width = 2").is_err());
+        assert!(Config::parse("tile_size = 256").is_ok());
+
+        let err = Config::parse("tile_size = 500").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`world_width` must be at least 3, but `window_width / tile_size` gives 2"
+        );
+
+        let config = Config::parse("world_width = 100\nworld_height = 100\ntile_size = 500");
+        assert_eq!(config.unwrap().world_size().unwrap(), (100, 100));
+    }
+}
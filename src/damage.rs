@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use sdl2::rect::{Point, Rect};
+
+pub const DAMAGE_BLOCK_SIZE: i32 = 32;
+
+pub enum Damage {
+    Full,
+    Partial {
+        tiles: Vec<Point>,
+        regions: Vec<Rect>,
+    },
+}
+
+// Tracks which tiles changed since the last render, grouped into square blocks
+// so the renderer can upload a few texture regions instead of the whole world.
+pub struct DamageTracker {
+    full: bool,
+    capacity: usize,
+    tiles: Vec<Point>,
+    blocks: HashSet<(i32, i32)>,
+}
+
+impl DamageTracker {
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        DamageTracker {
+            full: true,
+            capacity: usize::from(width) * usize::from(height),
+            tiles: Vec::new(),
+            blocks: HashSet::new(),
+        }
+    }
+
+    pub fn mark(&mut self, position: Point) {
+        if self.full {
+            return;
+        }
+
+        // Once more tiles changed than a full redraw would touch (e.g. when
+        // nothing renders in between steps), stop tracking them individually.
+        if self.tiles.len() >= self.capacity {
+            self.mark_all();
+            return;
+        }
+
+        self.tiles.push(position);
+        self.blocks.insert((
+            position.x().div_euclid(DAMAGE_BLOCK_SIZE),
+            position.y().div_euclid(DAMAGE_BLOCK_SIZE),
+        ));
+    }
+
+    pub fn mark_all(&mut self) {
+        self.full = true;
+        self.tiles.clear();
+        self.blocks.clear();
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    pub fn take(&mut self, width: u16, height: u16) -> Damage {
+        if std::mem::take(&mut self.full) {
+            return Damage::Full;
+        }
+
+        let bounds = Rect::new(0, 0, width.into(), height.into());
+        let block_size = DAMAGE_BLOCK_SIZE as u32;
+        let mut regions: Vec<Rect> = self
+            .blocks
+            .drain()
+            .filter_map(|(bx, by)| {
+                let block = Rect::new(
+                    bx * DAMAGE_BLOCK_SIZE,
+                    by * DAMAGE_BLOCK_SIZE,
+                    block_size,
+                    block_size,
+                );
+                block.intersection(bounds)
+            })
+            .collect();
+        regions.sort_by_key(|region| (region.y(), region.x()));
+
+        Damage::Partial {
+            tiles: std::mem::take(&mut self.tiles),
+            regions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(damage: Damage) -> (Vec<Point>, Vec<Rect>) {
+        match damage {
+            Damage::Full => panic!("expected partial damage"),
+            Damage::Partial { tiles, regions } => (tiles, regions),
+        }
+    }
+
+    #[test]
+    fn starts_fully_damaged() {
+        let mut damage = DamageTracker::new(10, 10);
+        damage.mark(Point::new(1, 1));
+        assert!(matches!(damage.take(10, 10), Damage::Full));
+
+        let (tiles, regions) = partial(damage.take(10, 10));
+        assert!(tiles.is_empty());
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn groups_tiles_into_blocks_clipped_to_the_world() {
+        let mut damage = DamageTracker::new(40, 40);
+        damage.take(40, 40);
+        damage.mark(Point::new(1, 1));
+        damage.mark(Point::new(2, 3));
+        damage.mark(Point::new(35, 33));
+
+        let (tiles, regions) = partial(damage.take(40, 40));
+        assert_eq!(
+            tiles,
+            [Point::new(1, 1), Point::new(2, 3), Point::new(35, 33)]
+        );
+        assert_eq!(regions, [Rect::new(0, 0, 32, 32), Rect::new(32, 32, 8, 8)]);
+    }
+
+    #[test]
+    fn falls_back_to_full_damage_past_capacity() {
+        let mut damage = DamageTracker::new(2, 2);
+        damage.take(2, 2);
+        for _ in 0..5 {
+            damage.mark(Point::new(0, 0));
+        }
+        assert!(matches!(damage.take(2, 2), Damage::Full));
+
+        damage.mark(Point::new(1, 0));
+        damage.mark_all();
+        assert!(matches!(damage.take(2, 2), Damage::Full));
+    }
+}
@@ -1,6 +1,6 @@
 use sdl2::rect::Point;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Direction {
     Up = 0,
     Right = 1,
@@ -9,7 +9,7 @@ pub enum Direction {
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RelativeDirection {
     Forward = 0,
     Right = 1,
@@ -54,6 +54,25 @@ impl From<Direction> for RelativeDirection {
 }
 
 impl Direction {
+    // The direction of a single step between two orthogonally adjacent points.
+    #[must_use]
+    pub fn between(from: Point, to: Point) -> Option<Direction> {
+        match (to.x() - from.x(), to.y() - from.y()) {
+            (0, -1) => Some(Direction::Up),
+            (1, 0) => Some(Direction::Right),
+            (0, 1) => Some(Direction::Down),
+            (-1, 0) => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    // The turn that changes heading from `self` to `target`.
+    #[must_use]
+    pub fn relative_to(self, target: Direction) -> RelativeDirection {
+        let i = (target as usize + 4 - self as usize) % 4;
+        Direction::try_from(i).expect("invalid direction").into()
+    }
+
     #[must_use]
     pub fn rotate(&self, offset: RelativeDirection) -> Direction {
         let di = *self as usize;
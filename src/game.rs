@@ -0,0 +1,546 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+
+use crate::{
+    config::Config, Controls, Corner, Death, DeathCause, HudPanel, PlayerBrain, World, Wyrm,
+};
+
+// Points for each wyrm a player beats in a fight; food is worth one.
+pub const KILL_SCORE: u32 = 5;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum GameMode {
+    // Just watch the simulation.
+    #[default]
+    Off,
+    // One keyboard-controlled wyrm competing with the others for food.
+    Snake,
+    // Two players on one keyboard, playing rounds against each other.
+    Versus,
+}
+
+impl FromStr for GameMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(GameMode::Off),
+            "snake" => Ok(GameMode::Snake),
+            "versus" => Ok(GameMode::Versus),
+            _ => bail!("expected `off`, `snake` or `versus`"),
+        }
+    }
+}
+
+// How a versus round is won.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum VersusRule {
+    // First to grow to `versus_target_length`; dead players respawn.
+    #[default]
+    Length,
+    // Last player alive; dead players sit out the rest of the round.
+    Survival,
+}
+
+impl FromStr for VersusRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "length" => Ok(VersusRule::Length),
+            "survival" => Ok(VersusRule::Survival),
+            _ => bail!("expected `length` or `survival`"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameState {
+    Playing,
+    // A versus round was decided; `None` means a draw.
+    RoundOver { winner: Option<usize> },
+    GameOver,
+}
+
+pub struct Player {
+    pub name: String,
+    pub controls: Controls,
+    pub wyrm_id: Option<u16>,
+    pub lives: u32,
+    pub score: u32,
+    pub round_wins: u32,
+    // Points from wyrms already lost.
+    banked_score: u32,
+}
+
+impl Player {
+    fn new(name: &str, lives: u32) -> Self {
+        Player {
+            name: name.to_string(),
+            controls: Controls::default(),
+            wyrm_id: None,
+            lives,
+            score: 0,
+            round_wins: 0,
+            banked_score: 0,
+        }
+    }
+
+    // Places a fresh wyrm for the player, unless the world is too crowded
+    // right now; `Game::update` tries again on the next frame.
+    fn spawn(&mut self, world: &mut World) -> Result<()> {
+        let Some(position) = world.random_open_position()? else {
+            return Ok(());
+        };
+
+        self.controls.clear();
+        let brain = Box::new(PlayerBrain::new(self.controls.clone()));
+        self.wyrm_id = world.create_wyrm_with_brain(position, brain)?;
+        Ok(())
+    }
+
+    // Removes the player's wyrm, if it's still alive, without it costing a
+    // life.
+    fn retire(&mut self, world: &mut World) -> Result<()> {
+        if let Some(wyrm_id) = self.wyrm_id.take() {
+            self.banked_score = self.score;
+            if world.wyrms.contains_key(&wyrm_id) {
+                world.kill_wyrm(wyrm_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Scores the deaths since the last frame; returns whether the player's
+    // own wyrm was among them.
+    fn record_deaths(&mut self, deaths: &[Death]) -> bool {
+        let Some(wyrm_id) = self.wyrm_id else {
+            return false;
+        };
+
+        let mut died = false;
+        for death in deaths {
+            if death.cause == (DeathCause::Fight { winner: wyrm_id }) {
+                self.banked_score += KILL_SCORE;
+            }
+            if death.wyrm_id == wyrm_id {
+                self.banked_score += death.food_eaten;
+                self.wyrm_id = None;
+                died = true;
+            }
+        }
+
+        died
+    }
+
+    fn update_score(&mut self, world: &World) {
+        let food_eaten = self
+            .wyrm_id
+            .and_then(|wyrm_id| world.wyrms.get(&wyrm_id))
+            .map_or(0, |wyrm| wyrm.food_eaten);
+        self.score = self.banked_score + food_eaten;
+    }
+
+    fn length(&self, world: &World) -> usize {
+        self.wyrm_id
+            .and_then(|wyrm_id| world.wyrms.get(&wyrm_id))
+            .map_or(0, Wyrm::size)
+    }
+}
+
+// Keyboard players on top of the simulation. In snake mode one player scores
+// by eating and winning fights and loses a life whenever their wyrm dies. In
+// versus mode two players play rounds under `rule` until one of them has won
+// `rounds` of them.
+pub struct Game {
+    pub mode: GameMode,
+    pub players: Vec<Player>,
+    pub state: GameState,
+    pub round: u32,
+    lives: u32,
+    rule: VersusRule,
+    target_length: usize,
+    rounds: u32,
+}
+
+impl Game {
+    pub fn new(world: &mut World, mode: GameMode, config: &Config) -> Result<Self> {
+        let mut game = Game {
+            mode,
+            players: Vec::new(),
+            state: GameState::Playing,
+            round: 0,
+            lives: config.player_lives,
+            rule: config.versus_rule,
+            target_length: config.versus_target_length,
+            rounds: config.versus_rounds,
+        };
+        game.restart(world)?;
+        Ok(game)
+    }
+
+    pub fn restart(&mut self, world: &mut World) -> Result<()> {
+        for player in &mut self.players {
+            player.retire(world)?;
+        }
+
+        self.players = match self.mode {
+            GameMode::Off => Vec::new(),
+            GameMode::Snake => vec![Player::new("player", self.lives)],
+            GameMode::Versus => vec![Player::new("wasd", 1), Player::new("arrows", 1)],
+        };
+        self.round = 0;
+        self.start_round(world)
+    }
+
+    // Clears the players' wyrms and starts them over at length 1.
+    pub fn start_round(&mut self, world: &mut World) -> Result<()> {
+        for player in &mut self.players {
+            player.retire(world)?;
+            if self.mode == GameMode::Versus {
+                player.lives = 1;
+            }
+            player.spawn(world)?;
+        }
+
+        self.round += 1;
+        self.state = GameState::Playing;
+        Ok(())
+    }
+
+    // Moves on from a decided round or a finished game.
+    pub fn proceed(&mut self, world: &mut World) -> Result<()> {
+        match self.state {
+            GameState::Playing => Ok(()),
+            GameState::RoundOver { .. } => self.start_round(world),
+            GameState::GameOver => self.restart(world),
+        }
+    }
+
+    // Scores the deaths since the last frame, respawns players and decides
+    // whether the round or game is over.
+    pub fn update(&mut self, world: &mut World, deaths: &[Death]) -> Result<()> {
+        if self.state != GameState::Playing {
+            return Ok(());
+        }
+
+        // Versus players only run out of lives under the survival rule.
+        let loses_life = self.mode == GameMode::Snake || self.rule == VersusRule::Survival;
+        for player in &mut self.players {
+            if player.record_deaths(deaths) && loses_life {
+                player.lives = player.lives.saturating_sub(1);
+            }
+            if player.lives > 0 && player.wyrm_id.is_none() {
+                player.spawn(world)?;
+            }
+            player.update_score(world);
+        }
+
+        match self.mode {
+            GameMode::Off => {}
+            GameMode::Snake => {
+                if self.players.iter().all(|player| player.lives == 0) {
+                    self.state = GameState::GameOver;
+                }
+            }
+            GameMode::Versus => {
+                if let Some(winners) = self.round_winners(world) {
+                    let winner = match winners[..] {
+                        [winner] => Some(winner),
+                        _ => None,
+                    };
+                    self.end_round(winner);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The players who won the round once it's decided: one winner, or
+    // several (or none) for a draw.
+    fn round_winners(&self, world: &World) -> Option<Vec<usize>> {
+        let players = 0..self.players.len();
+        match self.rule {
+            VersusRule::Length => {
+                // Players reaching the target on the same frame are ranked by
+                // length.
+                let lengths: Vec<usize> = self
+                    .players
+                    .iter()
+                    .map(|player| player.length(world))
+                    .collect();
+                let longest = *lengths.iter().max()?;
+                if longest < self.target_length {
+                    return None;
+                }
+                Some(players.filter(|&i| lengths[i] == longest).collect())
+            }
+            VersusRule::Survival => {
+                let alive: Vec<usize> = players.filter(|&i| self.players[i].lives > 0).collect();
+                (alive.len() <= 1).then_some(alive)
+            }
+        }
+    }
+
+    fn end_round(&mut self, winner: Option<usize>) {
+        if let Some(i) = winner {
+            self.players[i].round_wins += 1;
+        }
+
+        let match_won = self
+            .players
+            .iter()
+            .any(|player| player.round_wins >= self.rounds);
+        self.state = if match_won {
+            GameState::GameOver
+        } else {
+            GameState::RoundOver { winner }
+        };
+    }
+
+    fn scoreboard(&self, world: &World) -> Vec<String> {
+        match self.mode {
+            GameMode::Off => Vec::new(),
+            GameMode::Snake => self
+                .players
+                .iter()
+                .flat_map(|player| {
+                    [
+                        format!("score: {}", player.score),
+                        format!("lives: {}", player.lives),
+                    ]
+                })
+                .collect(),
+            GameMode::Versus => {
+                let goal = match self.rule {
+                    VersusRule::Length => format!("length {} wins", self.target_length),
+                    VersusRule::Survival => "last one alive wins".to_string(),
+                };
+                let mut lines = vec![format!("round {}: {goal}", self.round)];
+                lines.extend(self.players.iter().map(|player| {
+                    format!(
+                        "{}: {}/{} rounds, length {}, score {}",
+                        player.name,
+                        player.round_wins,
+                        self.rounds,
+                        player.length(world),
+                        player.score
+                    )
+                }));
+                lines
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn panels(&self, world: &World) -> Vec<HudPanel> {
+        let mut panels = vec![HudPanel::new(Corner::BottomLeft, self.scoreboard(world))];
+        let message = match self.state {
+            GameState::Playing => return panels,
+            GameState::RoundOver { winner } => {
+                let result = match winner {
+                    Some(i) => format!("{} wins round {}", self.players[i].name, self.round),
+                    None => format!("round {} is a draw", self.round),
+                };
+                vec![result, "press enter for the next round".to_string()]
+            }
+            GameState::GameOver => {
+                let result = match self.mode {
+                    GameMode::Versus => self
+                        .players
+                        .iter()
+                        .find(|player| player.round_wins >= self.rounds)
+                        .map_or("draw".to_string(), |player| {
+                            format!("{} wins the match", player.name)
+                        }),
+                    _ => format!(
+                        "score: {}",
+                        self.players.iter().map(|player| player.score).sum::<u32>()
+                    ),
+                };
+                vec![
+                    "game over".to_string(),
+                    result,
+                    "press enter to play again".to_string(),
+                ]
+            }
+        };
+
+        panels.push(HudPanel::new(Corner::Center, message));
+        panels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::rect::Point;
+
+    use super::*;
+    use crate::{config::WorldConfig, Direction, Map};
+
+    fn setup(mode: GameMode, config: &Config) -> (World, Game) {
+        let map = ["############"]
+            .into_iter()
+            .chain(["#..........#"; 10])
+            .chain(["############"])
+            .collect::<Vec<_>>()
+            .join("\n");
+        let world_config = WorldConfig {
+            spawn_interval: 1000,
+            poop_chance: 0.0,
+            seed: Some(1),
+            ..WorldConfig::default()
+        };
+        let mut world = World::from_map(&Map::parse(&map).unwrap(), world_config);
+        let game = Game::new(&mut world, mode, config).unwrap();
+        (world, game)
+    }
+
+    fn death(wyrm_id: u16, cause: DeathCause, food_eaten: u32) -> Death {
+        Death {
+            wyrm_id,
+            step: 0,
+            cause,
+            length: 1,
+            food_eaten,
+            position: Point::new(1, 1),
+        }
+    }
+
+    #[test]
+    fn snake_scores_food_and_fights() {
+        let (mut world, mut game) = setup(GameMode::Snake, &Config::default());
+        let wyrm_id = game.players[0].wyrm_id.unwrap();
+        world.wyrms.get_mut(&wyrm_id).unwrap().food_eaten = 3;
+        game.update(&mut world, &[]).unwrap();
+        assert_eq!(game.players[0].score, 3);
+
+        let beaten = death(wyrm_id + 100, DeathCause::Fight { winner: wyrm_id }, 7);
+        game.update(&mut world, &[beaten]).unwrap();
+        assert_eq!(game.players[0].score, 3 + KILL_SCORE);
+    }
+
+    #[test]
+    fn snake_loses_lives_until_game_over() {
+        let config = Config {
+            player_lives: 2,
+            ..Config::default()
+        };
+        let (mut world, mut game) = setup(GameMode::Snake, &config);
+
+        let first = game.players[0].wyrm_id.unwrap();
+        world.kill_wyrm(first).unwrap();
+        game.update(&mut world, &[death(first, DeathCause::Wall, 2)])
+            .unwrap();
+        let player = &game.players[0];
+        assert_eq!(player.lives, 1);
+        assert_eq!(player.score, 2);
+        assert!(player.wyrm_id.is_some_and(|id| id != first));
+        assert_eq!(game.state, GameState::Playing);
+
+        // The score from earlier wyrms is kept.
+        let second = game.players[0].wyrm_id.unwrap();
+        world.kill_wyrm(second).unwrap();
+        game.update(&mut world, &[death(second, DeathCause::OwnBody, 1)])
+            .unwrap();
+        let player = &game.players[0];
+        assert_eq!(player.lives, 0);
+        assert_eq!(player.score, 3);
+        assert_eq!(player.wyrm_id, None);
+        assert_eq!(game.state, GameState::GameOver);
+
+        game.proceed(&mut world).unwrap();
+        assert_eq!(game.players[0].lives, 2);
+        assert_eq!(game.players[0].score, 0);
+        assert_eq!(game.state, GameState::Playing);
+    }
+
+    fn versus(rule: VersusRule) -> (World, Game) {
+        let config = Config {
+            versus_rule: rule,
+            versus_target_length: 3,
+            versus_rounds: 2,
+            ..Config::default()
+        };
+        setup(GameMode::Versus, &config)
+    }
+
+    // Swaps the player's wyrm for one of length 3 along the given row.
+    fn grow(world: &mut World, player: &mut Player, y: i32) {
+        if let Some(wyrm_id) = player.wyrm_id {
+            world.kill_wyrm(wyrm_id).unwrap();
+        }
+        let segments = [Point::new(3, y), Point::new(2, y), Point::new(1, y)];
+        player.wyrm_id = world
+            .create_wyrm_from_segments(&segments, Direction::Right)
+            .unwrap();
+        assert!(player.wyrm_id.is_some());
+    }
+
+    #[test]
+    fn versus_rounds_go_to_the_first_to_reach_the_length() {
+        let (mut world, mut game) = versus(VersusRule::Length);
+        game.update(&mut world, &[]).unwrap();
+        assert_eq!(game.state, GameState::Playing);
+
+        grow(&mut world, &mut game.players[1], 1);
+        game.update(&mut world, &[]).unwrap();
+        assert_eq!(game.state, GameState::RoundOver { winner: Some(1) });
+        assert_eq!(game.players[1].round_wins, 1);
+
+        game.proceed(&mut world).unwrap();
+        assert_eq!(game.round, 2);
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.players[1].length(&world), 1);
+    }
+
+    #[test]
+    fn versus_rounds_can_be_drawn() {
+        let (mut world, mut game) = versus(VersusRule::Length);
+        grow(&mut world, &mut game.players[0], 1);
+        grow(&mut world, &mut game.players[1], 10);
+        game.update(&mut world, &[]).unwrap();
+        assert_eq!(game.state, GameState::RoundOver { winner: None });
+        assert!(game.players.iter().all(|player| player.round_wins == 0));
+
+        // Under the survival rule, players dying on the same frame draw.
+        let (mut world, mut game) = versus(VersusRule::Survival);
+        let deaths: Vec<Death> = game
+            .players
+            .iter()
+            .map(|player| death(player.wyrm_id.unwrap(), DeathCause::Wall, 0))
+            .collect();
+        for death in &deaths {
+            world.kill_wyrm(death.wyrm_id).unwrap();
+        }
+        game.update(&mut world, &deaths).unwrap();
+        assert_eq!(game.state, GameState::RoundOver { winner: None });
+    }
+
+    #[test]
+    fn versus_match_ends_after_enough_round_wins() {
+        let (mut world, mut game) = versus(VersusRule::Survival);
+        for round in 1..=2 {
+            assert_eq!(game.round, round);
+            let loser = game.players[1].wyrm_id.unwrap();
+            world.kill_wyrm(loser).unwrap();
+            game.update(&mut world, &[death(loser, DeathCause::Wall, 0)])
+                .unwrap();
+            // The loser sits out the rest of the round.
+            assert_eq!(game.players[1].wyrm_id, None);
+            if round == 1 {
+                assert_eq!(game.state, GameState::RoundOver { winner: Some(0) });
+                game.proceed(&mut world).unwrap();
+            }
+        }
+        assert_eq!(game.state, GameState::GameOver);
+        assert_eq!(game.players[0].round_wins, 2);
+
+        game.proceed(&mut world).unwrap();
+        assert_eq!(game.round, 1);
+        assert!(game.players.iter().all(|player| player.round_wins == 0));
+    }
+}
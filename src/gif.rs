@@ -0,0 +1,430 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{ensure, Context, Result};
+use sdl2::pixels::PixelFormatEnum;
+
+use crate::{capture, Color, PixelBuffer, World};
+
+const MAX_PALETTE_SIZE: usize = 256;
+const MAX_CODE_SIZE: u32 = 12;
+const MAX_CODES: u16 = 1 << MAX_CODE_SIZE;
+const MAX_SUB_BLOCK: usize = 255;
+// Hundredths of a second; many viewers show shorter delays much slower.
+const MIN_FRAME_DELAY: f64 = 2.0;
+
+// Writes an endlessly looping animated GIF. Every frame carries its own
+// color table, so palettes can change as wyrms come and go.
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    pub fn new(mut writer: W, width: u32, height: u32) -> Result<Self> {
+        let width = u16::try_from(width).context("GIF frames can be at most 65535 pixels wide")?;
+        let height =
+            u16::try_from(height).context("GIF frames can be at most 65535 pixels tall")?;
+
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&width.to_le_bytes())?;
+        writer.write_all(&height.to_le_bytes())?;
+        // No global color table, background color 0, square pixels.
+        writer.write_all(&[0x00, 0, 0])?;
+        // NETSCAPE2.0 application extension: loop forever.
+        writer.write_all(&[0x21, 0xff, 11])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[3, 1, 0, 0, 0])?;
+
+        Ok(GifEncoder {
+            writer,
+            width,
+            height,
+        })
+    }
+
+    // Adds an RGB24 frame shown for `delay` hundredths of a second. The color
+    // table holds the frame's 256 most common colors; any others are mapped to
+    // the closest of them.
+    pub fn add_frame(&mut self, buffer: &PixelBuffer, delay: u16) -> Result<()> {
+        ensure!(
+            buffer.format == PixelFormatEnum::RGB24,
+            "GIF frames must be RGB24, not {:?}",
+            buffer.format
+        );
+        ensure!(
+            (buffer.width, buffer.height) == (self.width.into(), self.height.into()),
+            "GIF frame is {}x{}, expected {}x{}",
+            buffer.width,
+            buffer.height,
+            self.width,
+            self.height
+        );
+
+        let palette = frame_palette(buffer);
+        ensure!(!palette.is_empty(), "GIF frames need at least one pixel");
+        let indices = quantize(buffer, &palette);
+        let table_bits = palette.len().next_power_of_two().trailing_zeros().max(1);
+
+        // Graphic control extension carrying the frame delay.
+        self.writer.write_all(&[0x21, 0xf9, 4, 0x00])?;
+        self.writer.write_all(&delay.to_le_bytes())?;
+        self.writer.write_all(&[0, 0])?;
+
+        // Image descriptor covering the whole screen, with a local color table.
+        self.writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.writer.write_all(&self.width.to_le_bytes())?;
+        self.writer.write_all(&self.height.to_le_bytes())?;
+        #[allow(clippy::cast_possible_truncation)]
+        self.writer.write_all(&[0x80 | (table_bits - 1) as u8])?;
+        for i in 0..1 << table_bits {
+            let color = palette.get(i).copied().unwrap_or(Color::BLACK);
+            self.writer.write_all(&[color.r, color.g, color.b])?;
+        }
+
+        let min_code_size = table_bits.max(2);
+        #[allow(clippy::cast_possible_truncation)]
+        self.writer.write_all(&[min_code_size as u8])?;
+        for block in lzw_encode(&indices, min_code_size).chunks(MAX_SUB_BLOCK) {
+            #[allow(clippy::cast_possible_truncation)]
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn rgb_pixels(buffer: &PixelBuffer) -> impl Iterator<Item = [u8; 3]> + '_ {
+    let row_size = buffer.width as usize * 3;
+    buffer
+        .data
+        .chunks(buffer.pitch)
+        .take(buffer.height as usize)
+        .flat_map(move |row| row[..row_size].chunks(3))
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+}
+
+// The colors of a frame, most common first, up to as many as a color table
+// holds.
+fn frame_palette(buffer: &PixelBuffer) -> Vec<Color> {
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for rgb in rgb_pixels(buffer) {
+        *counts.entry(rgb).or_default() += 1;
+    }
+
+    let mut colors: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+    colors.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    colors
+        .into_iter()
+        .take(MAX_PALETTE_SIZE)
+        .map(|([r, g, b], _)| Color::RGB(r, g, b))
+        .collect()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn quantize(buffer: &PixelBuffer, palette: &[Color]) -> Vec<u8> {
+    let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+    for (i, color) in palette.iter().enumerate() {
+        lookup.insert([color.r, color.g, color.b], i as u8);
+    }
+
+    rgb_pixels(buffer)
+        .map(|rgb| {
+            *lookup
+                .entry(rgb)
+                .or_insert_with(|| closest_color(palette, rgb))
+        })
+        .collect()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn closest_color(palette: &[Color], [r, g, b]: [u8; 3]) -> u8 {
+    let distance = |color: &Color| {
+        let dr = i32::from(color.r) - i32::from(r);
+        let dg = i32::from(color.g) - i32::from(g);
+        let db = i32::from(color.b) - i32::from(b);
+        dr * dr + dg * dg + db * db
+    };
+
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .map_or(0, |(i, _)| i as u8)
+}
+
+// Packs variable-width codes least significant bit first, as GIF expects.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer.to_le_bytes()[0]);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer.to_le_bytes()[0]);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear_code: u16 = 1 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut output = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end_code + 1;
+    let mut code_size = min_code_size + 1;
+
+    output.write(clear_code, code_size);
+    let Some((&first, rest)) = indices.split_first() else {
+        output.write(end_code, code_size);
+        return output.finish();
+    };
+
+    let mut prefix = u16::from(first);
+    for &index in rest {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        output.write(prefix, code_size);
+        if next_code == MAX_CODES {
+            output.write(clear_code, code_size);
+            table.clear();
+            next_code = end_code + 1;
+            code_size = min_code_size + 1;
+        } else {
+            table.insert((prefix, index), next_code);
+            // The decoder adds each code one step later than the encoder, so
+            // widen only once the new code no longer fits.
+            if next_code == 1 << code_size && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+            next_code += 1;
+        }
+        prefix = u16::from(index);
+    }
+
+    output.write(prefix, code_size);
+    output.write(end_code, code_size);
+    output.finish()
+}
+
+// Records a world into a GIF file, keeping at most one frame out of every
+// `frame_skip + 1` steps. Each frame is shown for as long as the steps until
+// the next one take at the configured step rate, however many steps actually
+// passed in between, so playback speed doesn't depend on how often frames are
+// offered.
+pub struct GifRecording {
+    encoder: GifEncoder<BufWriter<File>>,
+    scale: u32,
+    frame_skip: u32,
+    // Hundredths of a second per step.
+    step_delay: f64,
+    // Delay owed to or by earlier frames from rounding.
+    carry: f64,
+    // The latest frame and its step, written once the next frame says how
+    // long to show it.
+    pending: Option<(PixelBuffer, usize)>,
+    pub frames: u64,
+}
+
+pub struct NewGifRecordingParams<'a> {
+    pub path: &'a Path,
+    pub world_size: (u16, u16),
+    pub scale: u32,
+    pub frame_skip: u32,
+    pub step_time: Duration,
+}
+
+impl GifRecording {
+    pub fn create(params: &NewGifRecordingParams) -> Result<Self> {
+        let file = File::create(params.path)
+            .with_context(|| format!("failed to create GIF file {}", params.path.display()))?;
+        let (width, height) = params.world_size;
+        let encoder = GifEncoder::new(
+            BufWriter::new(file),
+            u32::from(width) * params.scale,
+            u32::from(height) * params.scale,
+        )?;
+
+        Ok(GifRecording {
+            encoder,
+            scale: params.scale,
+            frame_skip: params.frame_skip,
+            step_delay: params.step_time.as_secs_f64() * 100.0,
+            carry: 0.0,
+            pending: None,
+            frames: 0,
+        })
+    }
+
+    pub fn record(&mut self, world: &World) -> Result<()> {
+        let step = world.current_step();
+        let frame_steps = self.frame_skip as usize + 1;
+        if let Some((_, pending_step)) = &self.pending {
+            if step < pending_step + frame_steps {
+                return Ok(());
+            }
+        }
+
+        let image = capture(world, self.scale)?;
+        if let Some((previous, previous_step)) = self.pending.replace((image, step)) {
+            self.write_frame(&previous, step - previous_step)?;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_sign_loss)]
+    fn write_frame(&mut self, image: &PixelBuffer, steps: usize) -> Result<()> {
+        let exact = steps as f64 * self.step_delay + self.carry;
+        let delay = exact.round().clamp(MIN_FRAME_DELAY, f64::from(u16::MAX));
+        self.carry = exact - delay;
+        self.encoder.add_frame(image, delay as u16)
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        if let Some((image, _)) = self.pending.take() {
+            self.write_frame(&image, self.frame_skip as usize + 1)?;
+        }
+        self.encoder.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A plain GIF LZW decoder, the inverse of `lzw_encode`.
+    fn lzw_decode(data: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear_code = 1usize << min_code_size;
+        let end_code = clear_code + 1;
+        let initial: Vec<Vec<u8>> = (0..=end_code)
+            .map(|code| u8::try_from(code).map_or_else(|_| Vec::new(), |byte| vec![byte]))
+            .collect();
+        let mut table = initial.clone();
+        let mut code_size = min_code_size + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = Vec::new();
+        let mut bit = 0;
+        loop {
+            let code = (0..code_size).fold(0, |code, i| {
+                let position = bit + i as usize;
+                let set = data[position / 8] >> (position % 8) & 1;
+                code | usize::from(set) << i
+            });
+            bit += code_size as usize;
+
+            if code == clear_code {
+                table.clone_from(&initial);
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code {
+                return output;
+            }
+
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [previous.as_slice(), &previous[..1]].concat(),
+                (None, None) => panic!("code {code} isn't in the table"),
+            };
+            output.extend_from_slice(&entry);
+            if let Some(mut previous) = previous.take() {
+                if table.len() < usize::from(MAX_CODES) {
+                    previous.push(entry[0]);
+                    table.push(previous);
+                    if table.len() == 1 << code_size && code_size < MAX_CODE_SIZE {
+                        code_size += 1;
+                    }
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        let repetitive: Vec<u8> = (0..1000).map(|i| [0, 1, 0, 1, 1][i % 5]).collect();
+        // Enough varied data to fill the code table and reset it.
+        let mut state = 1u32;
+        let noisy: Vec<u8> = (0..50_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                state.to_le_bytes()[2]
+            })
+            .collect();
+
+        for (indices, min_code_size) in [
+            (Vec::new(), 2),
+            (vec![3], 2),
+            (vec![1, 1, 1, 1, 1, 1, 1], 2),
+            (repetitive, 2),
+            (noisy, 8),
+        ] {
+            let encoded = lzw_encode(&indices, min_code_size);
+            assert_eq!(lzw_decode(&encoded, min_code_size), indices);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[test]
+    fn palette_orders_colors_by_count() {
+        let mut buffer = PixelBuffer::new(4, 1, PixelFormatEnum::RGB24).unwrap();
+        let (red, blue) = (Color::RGB(255, 0, 0), Color::RGB(0, 0, 255));
+        buffer.put_pixel(0, 0, blue);
+        buffer.put_pixel(1, 0, red);
+        buffer.put_pixel(2, 0, red);
+        assert_eq!(frame_palette(&buffer), vec![red, Color::RGB(0, 0, 0), blue]);
+
+        let mut buffer = PixelBuffer::new(300, 1, PixelFormatEnum::RGB24).unwrap();
+        for x in 0..300 {
+            buffer.put_pixel(x, 0, Color::RGB((x % 256) as u8, (x / 256) as u8, 0));
+        }
+        assert_eq!(frame_palette(&buffer).len(), MAX_PALETTE_SIZE);
+    }
+
+    #[test]
+    fn quantizes_to_closest_color() {
+        let mut buffer = PixelBuffer::new(2, 1, PixelFormatEnum::RGB24).unwrap();
+        buffer.put_pixel(0, 0, Color::RGB(250, 10, 0));
+        buffer.put_pixel(1, 0, Color::RGB(0, 0, 200));
+        let palette = [Color::RGB(0, 0, 255), Color::RGB(255, 0, 0)];
+        assert_eq!(quantize(&buffer, &palette), vec![1, 0]);
+    }
+}
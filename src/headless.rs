@@ -0,0 +1,120 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Result;
+
+use crate::{
+    config::Config, open_output, save_screenshot, tile, GifRecording, HeatmapKind, Map,
+    NewGifRecordingParams, NewVideoStreamParams, TextRenderer, VideoFormat, VideoStream, World,
+    STDOUT_PATH,
+};
+
+// Files written by a headless run; `None` skips that output.
+pub struct HeadlessOutputs<'a> {
+    pub map_path: Option<&'a Path>,
+    pub screenshot_path: Option<&'a Path>,
+    pub text_path: Option<&'a Path>,
+    pub gif_path: Option<&'a Path>,
+    pub video_path: Option<&'a Path>,
+    pub video_format: VideoFormat,
+    pub heatmap_path: Option<&'a Path>,
+    pub heatmap_kind: HeatmapKind,
+}
+
+pub fn run_headless(
+    config: &Config,
+    map: Option<&Map>,
+    steps: usize,
+    outputs: &HeadlessOutputs,
+) -> Result<()> {
+    // Only count heatmaps when they're saved at the end.
+    let mut config = config.clone();
+    config.world.heatmaps = outputs.heatmap_path.is_some();
+    let mut world = World::from_config(&config, map)?;
+    let mut recording = match outputs.gif_path {
+        Some(path) => Some(GifRecording::create(&NewGifRecordingParams {
+            path,
+            world_size: (world.width, world.height),
+            scale: config.recording_scale_factor(),
+            frame_skip: config.recording_frame_skip,
+            step_time: config.step_time(),
+        })?),
+        None => None,
+    };
+    let mut video = match outputs.video_path {
+        Some(path) => Some(VideoStream::new(
+            open_output(path)?,
+            &NewVideoStreamParams {
+                format: outputs.video_format,
+                world_size: (world.width, world.height),
+                scale: config.recording_scale_factor(),
+                frame_skip: config.recording_frame_skip,
+                fps: config.fps,
+            },
+        )?),
+        None => None,
+    };
+
+    for step in 0..=steps {
+        if step > 0 {
+            world.step()?;
+        }
+        if let Some(recording) = &mut recording {
+            recording.record(&world)?;
+        }
+        if let Some(video) = &mut video {
+            video.record(&world)?;
+        }
+    }
+
+    let final_map = world.to_map();
+    let food_count = final_map
+        .tiles
+        .iter()
+        .filter(|&&tile| tile == tile::FOOD)
+        .count();
+    let summary = format!(
+        "step {}: {} wyrms, {} food",
+        world.current_step(),
+        world.wyrms.len(),
+        food_count
+    );
+    // Keep the summary out of a video or text streamed to stdout.
+    let stdout = Some(Path::new(STDOUT_PATH));
+    if outputs.video_path == stdout || outputs.text_path == stdout {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+
+    if let Some(path) = outputs.map_path {
+        final_map.save(path)?;
+    }
+    if let Some(path) = outputs.screenshot_path {
+        let scale = config.screenshot_scale_factor((world.width, world.height));
+        save_screenshot(&world, scale, path)?;
+    }
+    if let Some(path) = outputs.text_path {
+        let mut renderer = TextRenderer::default();
+        world.render(&mut renderer)?;
+        let mut writer = open_output(path)?;
+        for line in renderer.lines() {
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()?;
+    }
+    if let (Some(path), Some(heatmap)) = (outputs.heatmap_path, &world.heatmap) {
+        heatmap.save(
+            outputs.heatmap_kind,
+            config.screenshot_scale_factor((world.width, world.height)),
+            path,
+        )?;
+    }
+    if let Some(recording) = recording {
+        recording.finish()?;
+    }
+    if let Some(video) = video {
+        video.finish()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Error, Result};
+use sdl2::{pixels::PixelFormatEnum, rect::Point};
+
+use crate::{
+    color,
+    config::PIXEL_FORMAT,
+    save_image, tile,
+    tiles::{chunk_location, CHUNK_AREA, CHUNK_SIZE},
+    Color, Damage, DamageTracker, ImageFormat, ImageRenderer, PixelBuffer, Renderer,
+};
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum HeatmapKind {
+    // Steps each tile spent under a wyrm segment.
+    #[default]
+    Visits,
+    // Deaths, counted where the wyrm's head was.
+    Deaths,
+    // Food eaten.
+    Food,
+}
+
+pub const HEATMAP_KINDS: [HeatmapKind; 3] =
+    [HeatmapKind::Visits, HeatmapKind::Deaths, HeatmapKind::Food];
+
+impl FromStr for HeatmapKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "visits" => Ok(HeatmapKind::Visits),
+            "deaths" => Ok(HeatmapKind::Deaths),
+            "food" => Ok(HeatmapKind::Food),
+            _ => bail!("expected `visits`, `deaths` or `food`"),
+        }
+    }
+}
+
+impl HeatmapKind {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            HeatmapKind::Visits => "visits",
+            HeatmapKind::Deaths => "deaths",
+            HeatmapKind::Food => "food",
+        }
+    }
+}
+
+// Per-tile event counters over the rendered area of a world. Counters are kept
+// in chunks that are only allocated once one of their tiles sees an event, so
+// the parts of a world no wyrm reaches cost nothing.
+pub struct Heatmap {
+    pub width: u16,
+    pub height: u16,
+    // Each tile's counts, in `HEATMAP_KINDS` order.
+    chunks: HashMap<(i32, i32), Box<[[u32; 3]]>>,
+    // Highest count of each kind, in `HEATMAP_KINDS` order.
+    maxima: [u32; 3],
+    // Tiles whose counts changed since the last `take_damage`, tracked only
+    // while an overlay asks for them.
+    damage: Option<DamageTracker>,
+}
+
+impl Heatmap {
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        Heatmap {
+            width,
+            height,
+            chunks: HashMap::new(),
+            maxima: [0; 3],
+            damage: None,
+        }
+    }
+
+    // Keeps the counts of tiles inside both the old and the new size.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.chunks.retain(|&(cx, cy), counts| {
+            for (i, tile_counts) in (0..).zip(counts.iter_mut()) {
+                let x = cx * CHUNK_SIZE + i % CHUNK_SIZE;
+                let y = cy * CHUNK_SIZE + i / CHUNK_SIZE;
+                if x >= i32::from(width) || y >= i32::from(height) {
+                    *tile_counts = [0; 3];
+                }
+            }
+            counts.iter().any(|tile_counts| *tile_counts != [0; 3])
+        });
+
+        self.maxima = [0; 3];
+        for tile_counts in self.chunks.values().flat_map(|counts| counts.iter()) {
+            for (max, &count) in self.maxima.iter_mut().zip(tile_counts) {
+                *max = (*max).max(count);
+            }
+        }
+        if let Some(damage) = &mut self.damage {
+            *damage = DamageTracker::new(width, height);
+        }
+    }
+
+    fn location(&self, position: Point) -> Option<((i32, i32), usize)> {
+        let (x, y) = (position.x(), position.y());
+        if x < 0 || y < 0 || x >= i32::from(self.width) || y >= i32::from(self.height) {
+            return None;
+        }
+
+        Some(chunk_location(position))
+    }
+
+    // Counts an event at `position`; events outside the map are ignored.
+    pub fn record(&mut self, kind: HeatmapKind, position: Point) {
+        let Some((key, i)) = self.location(position) else {
+            return;
+        };
+
+        let counts = self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| vec![[0; 3]; CHUNK_AREA].into_boxed_slice());
+        let count = &mut counts[i][kind as usize];
+        *count = count.saturating_add(1);
+        let max = &mut self.maxima[kind as usize];
+        *max = (*max).max(*count);
+        if let Some(damage) = &mut self.damage {
+            damage.mark(position);
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, kind: HeatmapKind, position: Point) -> u32 {
+        self.location(position)
+            .and_then(|(key, i)| Some(self.chunks.get(&key)?[i][kind as usize]))
+            .unwrap_or(0)
+    }
+
+    #[must_use]
+    pub fn max(&self, kind: HeatmapKind) -> u32 {
+        self.maxima[kind as usize]
+    }
+
+    // The tiles whose counts changed since the last call. The first call
+    // starts tracking them and reports full damage.
+    pub fn take_damage(&mut self) -> Damage {
+        let (width, height) = (self.width, self.height);
+        self.damage
+            .get_or_insert_with(|| DamageTracker::new(width, height))
+            .take(width, height)
+    }
+
+    // Stops tracking damage until the next `take_damage`, e.g. while no
+    // overlay is shown.
+    pub fn stop_tracking_damage(&mut self) {
+        self.damage = None;
+    }
+
+    // Draws the counts of `kind` as a color ramp, with tiles that never saw an
+    // event left transparent black. Like `World::render_changes`, only the
+    // damaged tiles are drawn.
+    pub fn draw(
+        &self,
+        kind: HeatmapKind,
+        renderer: &mut dyn Renderer,
+        damage: &Damage,
+    ) -> Result<()> {
+        renderer.begin_frame(self.width, self.height, damage)?;
+        let max = self.max(kind);
+        let mut draw_tile = |position: Point| {
+            let color = match self.get(kind, position) {
+                0 => Color::RGBA(0, 0, 0, 0),
+                count => heat_color(count, max),
+            };
+            renderer.draw_tile(position, tile::EMPTY, color);
+        };
+
+        match damage {
+            Damage::Full => {
+                for y in 0..i32::from(self.height) {
+                    for x in 0..i32::from(self.width) {
+                        draw_tile(Point::new(x, y));
+                    }
+                }
+            }
+            Damage::Partial { tiles, .. } => {
+                for &position in tiles {
+                    draw_tile(position);
+                }
+            }
+        }
+
+        renderer.end_frame(damage)
+    }
+
+    // Draws the counts as an image, each tile a `scale` × `scale` block.
+    pub fn render(
+        &self,
+        kind: HeatmapKind,
+        format: PixelFormatEnum,
+        scale: u32,
+    ) -> Result<PixelBuffer> {
+        let mut renderer = ImageRenderer::new(format, scale)?;
+        self.draw(kind, &mut renderer, &Damage::Full)?;
+        Ok(renderer.into_buffer())
+    }
+
+    // One row per tile: `x,y,visits,deaths,food`.
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "x,y,visits,deaths,food")?;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let position = Point::new(x.into(), y.into());
+                let [visits, deaths, food] = HEATMAP_KINDS.map(|kind| self.get(kind, position));
+                writeln!(writer, "{x},{y},{visits},{deaths},{food}")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn save_csv(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create heatmap file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        self.write_csv(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Saves all counts as CSV if `path` ends in `.csv`, or otherwise `kind`
+    // as an image in the format the extension names.
+    pub fn save(&self, kind: HeatmapKind, scale: u32, path: &Path) -> Result<()> {
+        let is_csv = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            return self.save_csv(path);
+        }
+
+        let format = ImageFormat::from_path(path)?;
+        save_image(&self.render(kind, PIXEL_FORMAT, scale)?, path, format)
+    }
+}
+
+// Maps a count onto a ramp from dark purple through red and orange to pale
+// yellow. The scale is logarithmic so hotspots don't wash out everything else.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn heat_color(count: u32, max: u32) -> Color {
+    let t = if max > 1 {
+        ((count as f32).ln_1p() / (max as f32).ln_1p()).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    color::color_from_oklch(0.3 + 0.65 * t, 0.12 + 0.06 * t, 300.0 + 160.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_chunks_on_first_event() {
+        let mut heatmap = Heatmap::new(u16::MAX, u16::MAX);
+        assert!(heatmap.chunks.is_empty());
+
+        let position = Point::new(1000, 2000);
+        heatmap.record(HeatmapKind::Deaths, position);
+        heatmap.record(HeatmapKind::Deaths, position);
+        heatmap.record(HeatmapKind::Food, Point::new(-1, 0));
+        assert_eq!(heatmap.chunks.len(), 1);
+        assert_eq!(heatmap.get(HeatmapKind::Deaths, position), 2);
+        assert_eq!(heatmap.get(HeatmapKind::Visits, position), 0);
+        assert_eq!(heatmap.max(HeatmapKind::Deaths), 2);
+        assert_eq!(heatmap.max(HeatmapKind::Food), 0);
+    }
+
+    #[test]
+    fn resize_keeps_counts_inside_both_sizes() {
+        let mut heatmap = Heatmap::new(100, 100);
+        for _ in 0..3 {
+            heatmap.record(HeatmapKind::Visits, Point::new(90, 10));
+        }
+        heatmap.record(HeatmapKind::Visits, Point::new(10, 10));
+
+        heatmap.resize(50, 50);
+        assert_eq!(heatmap.get(HeatmapKind::Visits, Point::new(10, 10)), 1);
+        assert_eq!(heatmap.max(HeatmapKind::Visits), 1);
+        assert_eq!(heatmap.chunks.len(), 1);
+
+        heatmap.resize(100, 100);
+        assert_eq!(heatmap.get(HeatmapKind::Visits, Point::new(90, 10)), 0);
+    }
+
+    #[test]
+    fn tracks_damage_only_while_asked() {
+        let mut heatmap = Heatmap::new(10, 10);
+        heatmap.record(HeatmapKind::Visits, Point::new(1, 1));
+        assert!(matches!(heatmap.take_damage(), Damage::Full));
+
+        heatmap.record(HeatmapKind::Visits, Point::new(2, 3));
+        let Damage::Partial { tiles, .. } = heatmap.take_damage() else {
+            panic!("expected partial damage");
+        };
+        assert_eq!(tiles, vec![Point::new(2, 3)]);
+
+        heatmap.stop_tracking_damage();
+        heatmap.record(HeatmapKind::Visits, Point::new(4, 4));
+        assert!(heatmap.damage.is_none());
+        assert!(matches!(heatmap.take_damage(), Damage::Full));
+    }
+
+    #[test]
+    fn renders_untouched_tiles_transparent() {
+        let mut heatmap = Heatmap::new(2, 1);
+        heatmap.record(HeatmapKind::Food, Point::new(1, 0));
+        let image = heatmap
+            .render(HeatmapKind::Food, PixelFormatEnum::RGBA32, 2)
+            .unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        assert_eq!(image.data[..4], [0, 0, 0, 0]);
+        let color = heat_color(1, 1);
+        assert_eq!(image.data[8..12], [color.r, color.g, color.b, 255]);
+    }
+
+    #[test]
+    fn writes_every_tile_as_csv() {
+        let mut heatmap = Heatmap::new(2, 1);
+        heatmap.record(HeatmapKind::Food, Point::new(1, 0));
+        let mut csv = Vec::new();
+        heatmap.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "x,y,visits,deaths,food\n0,0,0,0,0\n1,0,0,0,1\n"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use sdl2::{
+    pixels::PixelFormatEnum,
+    rect::Rect,
+    render::{BlendMode, Canvas, Texture, TextureCreator},
+    video::{Window, WindowContext},
+};
+
+use crate::{
+    font::{self, GLYPH_HEIGHT, LINE_HEIGHT},
+    renderer::replace_texture,
+    Color, PixelBuffer,
+};
+
+pub const HUD_MARGIN: i32 = 4;
+pub const HUD_PADDING: i32 = 3;
+
+const HUD_FORMAT: PixelFormatEnum = PixelFormatEnum::RGBA32;
+const HUD_BACKGROUND: Color = Color::RGBA(0, 0, 0, 160);
+const HUD_FOREGROUND: Color = Color::RGBA(255, 255, 255, 255);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HudItem {
+    Step,
+    Population,
+    Speed,
+    StepsPerSecond,
+    FramesPerSecond,
+    Tool,
+}
+
+impl FromStr for HudItem {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "step" => Ok(HudItem::Step),
+            "population" => Ok(HudItem::Population),
+            "speed" => Ok(HudItem::Speed),
+            "steps_per_second" => Ok(HudItem::StepsPerSecond),
+            "fps" => Ok(HudItem::FramesPerSecond),
+            "tool" => Ok(HudItem::Tool),
+            _ => bail!("unknown HUD item `{s}`"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    // Centered in the window, for messages that need attention.
+    Center,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HudPanel {
+    pub corner: Corner,
+    pub lines: Vec<String>,
+    pub swatch: Option<Color>,
+}
+
+impl HudPanel {
+    #[must_use]
+    pub fn new(corner: Corner, lines: Vec<String>) -> Self {
+        HudPanel {
+            corner,
+            lines,
+            swatch: None,
+        }
+    }
+}
+
+// Text panels drawn with the built-in bitmap font into a translucent overlay
+// texture, which is stretched over the window by `scale`.
+pub struct Hud {
+    scale: u32,
+    buffer: PixelBuffer,
+    texture: Texture,
+    panels: Option<Vec<HudPanel>>,
+}
+
+impl Hud {
+    pub fn new(
+        texture_creator: &TextureCreator<WindowContext>,
+        output_size: (u32, u32),
+        scale: u32,
+    ) -> Result<Self> {
+        let (buffer, texture) = Hud::create_surface(texture_creator, output_size, scale)?;
+        Ok(Hud {
+            scale,
+            buffer,
+            texture,
+            panels: None,
+        })
+    }
+
+    fn create_surface(
+        texture_creator: &TextureCreator<WindowContext>,
+        (output_width, output_height): (u32, u32),
+        scale: u32,
+    ) -> Result<(PixelBuffer, Texture)> {
+        let width = (output_width / scale).max(1);
+        let height = (output_height / scale).max(1);
+        let buffer = PixelBuffer::new(width, height, HUD_FORMAT)?;
+        let mut texture = texture_creator.create_texture_streaming(HUD_FORMAT, width, height)?;
+        texture.set_blend_mode(BlendMode::Blend);
+        Ok((buffer, texture))
+    }
+
+    fn draw_panel(&mut self, panel: &HudPanel) {
+        if panel.lines.is_empty() {
+            return;
+        }
+
+        let padding = HUD_PADDING.unsigned_abs();
+        let swatch_size = if panel.swatch.is_some() {
+            LINE_HEIGHT
+        } else {
+            0
+        };
+        let text_size = font::text_bounds(0, 0, &panel.lines);
+        let width = text_size.width() + swatch_size + 2 * padding;
+        let height = text_size.height() + padding;
+        let (buffer_width, buffer_height) = (
+            self.buffer.width.cast_signed(),
+            self.buffer.height.cast_signed(),
+        );
+        let (width_i, height_i) = (width.cast_signed(), height.cast_signed());
+        let (x, y) = match panel.corner {
+            Corner::TopLeft => (HUD_MARGIN, HUD_MARGIN),
+            Corner::TopRight => (buffer_width - HUD_MARGIN - width_i, HUD_MARGIN),
+            Corner::BottomLeft => (HUD_MARGIN, buffer_height - HUD_MARGIN - height_i),
+            Corner::BottomRight => (
+                buffer_width - HUD_MARGIN - width_i,
+                buffer_height - HUD_MARGIN - height_i,
+            ),
+            Corner::Center => ((buffer_width - width_i) / 2, (buffer_height - height_i) / 2),
+        };
+
+        self.buffer
+            .fill_rect(Rect::new(x, y, width, height), HUD_BACKGROUND);
+        for (i, line) in (0..).zip(&panel.lines) {
+            let line_y = y + HUD_PADDING + i * LINE_HEIGHT.cast_signed();
+            font::draw_text(
+                &mut self.buffer,
+                x + HUD_PADDING,
+                line_y,
+                line,
+                HUD_FOREGROUND,
+            );
+        }
+
+        if let Some(color) = panel.swatch {
+            let swatch_x = x + HUD_PADDING + text_size.width().cast_signed() + HUD_PADDING;
+            let swatch = Rect::new(swatch_x, y + HUD_PADDING, GLYPH_HEIGHT, GLYPH_HEIGHT);
+            self.buffer.fill_rect(swatch, color);
+        }
+    }
+
+    fn redraw(&mut self, panels: Vec<HudPanel>) -> Result<()> {
+        self.buffer.clear(Color::RGBA(0, 0, 0, 0));
+        for panel in &panels {
+            self.draw_panel(panel);
+        }
+
+        self.texture
+            .update(None, &self.buffer.data, self.buffer.pitch)?;
+        self.panels = Some(panels);
+        Ok(())
+    }
+
+    pub fn render(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        texture_creator: &TextureCreator<WindowContext>,
+        panels: Vec<HudPanel>,
+    ) -> Result<()> {
+        if panels.is_empty() {
+            return Ok(());
+        }
+
+        let output_size = canvas.output_size().map_err(Error::msg)?;
+        let expected_size = (output_size.0 / self.scale, output_size.1 / self.scale);
+        if expected_size != (self.buffer.width, self.buffer.height) {
+            let (buffer, texture) = Hud::create_surface(texture_creator, output_size, self.scale)?;
+            self.buffer = buffer;
+            replace_texture(&mut self.texture, texture);
+            self.panels = None;
+        }
+
+        if self.panels.as_ref() != Some(&panels) {
+            self.redraw(panels)?;
+        }
+
+        let width = self.buffer.width * self.scale;
+        let height = self.buffer.height * self.scale;
+        let destination = Rect::new(0, 0, width, height);
+        canvas
+            .copy(&self.texture, None, destination)
+            .map_err(Error::msg)
+    }
+}
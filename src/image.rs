@@ -0,0 +1,320 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use sdl2::pixels::PixelFormatEnum;
+
+use crate::PixelBuffer;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "png" => Ok(ImageFormat::Png),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => bail!("expected `png` or `ppm`"),
+        }
+    }
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        extension.parse().with_context(|| {
+            format!(
+                "can't tell the image format of {} from its extension",
+                path.display()
+            )
+        })
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+fn rgb_rows(buffer: &PixelBuffer) -> Result<impl Iterator<Item = &[u8]>> {
+    ensure!(
+        buffer.format == PixelFormatEnum::RGB24,
+        "images can only be written from RGB24 pixels, not {:?}",
+        buffer.format
+    );
+    let row_size = buffer.width as usize * 3;
+    Ok(buffer
+        .data
+        .chunks(buffer.pitch)
+        .take(buffer.height as usize)
+        .map(move |row| &row[..row_size]))
+}
+
+pub fn write_ppm<W: Write>(buffer: &PixelBuffer, writer: &mut W) -> Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", buffer.width, buffer.height)?;
+    for row in rgb_rows(buffer)? {
+        writer.write_all(row)?;
+    }
+
+    Ok(())
+}
+
+// Minimal PNG encoder: 8-bit RGB, no filtering, and a zlib stream made of
+// uncompressed deflate blocks. Files are large but need no compressor, and
+// rows are streamed out without copying the whole image.
+pub fn write_png<W: Write>(buffer: &PixelBuffer, writer: &mut W) -> Result<()> {
+    let rows = rgb_rows(buffer)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&buffer.width.to_be_bytes());
+    header.extend_from_slice(&buffer.height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    writer.write_all(&PNG_SIGNATURE)?;
+    write_chunk(writer, *b"IHDR", &header)?;
+    write_image_data(writer, rows)?;
+    write_chunk(writer, *b"IEND", &[])?;
+    Ok(())
+}
+
+pub fn save_image(buffer: &PixelBuffer, path: &Path, format: ImageFormat) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("failed to create image file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        ImageFormat::Png => write_png(buffer, &mut writer)?,
+        ImageFormat::Ppm => write_ppm(buffer, &mut writer)?,
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("failed to write image file {}", path.display()))
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: [u8; 4], data: &[u8]) -> Result<()> {
+    let length = u32::try_from(data.len()).context("PNG chunk is too large")?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(&kind)?;
+    writer.write_all(data)?;
+    let crc = crc32_update(crc32_update(!0, &kind), data);
+    writer.write_all(&(!crc).to_be_bytes())?;
+    Ok(())
+}
+
+// Writes the rows, each behind a "no filter" byte, as a zlib stream of stored
+// deflate blocks. Every block goes into its own IDAT chunk, so no more than a
+// block and a row are held in memory at a time.
+fn write_image_data<'a, W: Write>(
+    writer: &mut W,
+    rows: impl Iterator<Item = &'a [u8]>,
+) -> Result<()> {
+    let mut pending = Vec::with_capacity(2 * MAX_STORED_BLOCK);
+    let mut adler = 1;
+    let mut first = true;
+    for row in rows {
+        pending.push(0);
+        pending.extend_from_slice(row);
+        // Keep some data back so the last block is always written below.
+        while pending.len() > MAX_STORED_BLOCK {
+            let block = &pending[..MAX_STORED_BLOCK];
+            write_stored_block(writer, block, first, false, &mut adler)?;
+            pending.drain(..MAX_STORED_BLOCK);
+            first = false;
+        }
+    }
+
+    write_stored_block(writer, &pending, first, true, &mut adler)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn write_stored_block<W: Write>(
+    writer: &mut W,
+    block: &[u8],
+    first: bool,
+    last: bool,
+    adler: &mut u32,
+) -> Result<()> {
+    let mut data = Vec::with_capacity(block.len() + 11);
+    if first {
+        data.extend_from_slice(&[0x78, 0x01]);
+    }
+
+    let length = block.len() as u16;
+    data.push(u8::from(last));
+    data.extend_from_slice(&length.to_le_bytes());
+    data.extend_from_slice(&(!length).to_le_bytes());
+    data.extend_from_slice(block);
+
+    *adler = adler32_update(*adler, block);
+    if last {
+        data.extend_from_slice(&adler.to_be_bytes());
+    }
+    write_chunk(writer, *b"IDAT", &data)
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+#[allow(clippy::cast_possible_truncation)]
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn adler32_update(adler: u32, data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // The largest run of bytes that can be summed without overflowing `b`.
+    const NMAX: usize = 5552;
+
+    let (mut a, mut b) = (adler & 0xffff, adler >> 16);
+    for chunk in data.chunks(NMAX) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn computes_checksums() {
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32_update(1, b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn computes_adler32_in_pieces() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7).to_le_bytes()[0]).collect();
+        let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+            let a = (a + u32::from(byte)) % 65521;
+            (a, (b + a) % 65521)
+        });
+        let (head, tail) = data.split_at(12_345);
+        assert_eq!(adler32_update(1, &data), b << 16 | a);
+        assert_eq!(adler32_update(adler32_update(1, head), tail), b << 16 | a);
+    }
+
+    // Splits a PNG into its chunks, checking their CRCs.
+    fn png_chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = rest[4..8].try_into().unwrap();
+            let data = &rest[8..8 + length];
+            let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(!crc32_update(crc32_update(!0, &kind), data), crc);
+            chunks.push((kind, data));
+            rest = &rest[12 + length..];
+        }
+
+        chunks
+    }
+
+    // Inflates a zlib stream of stored deflate blocks, checking its Adler-32.
+    fn inflate_stored(mut zlib: &[u8]) -> Vec<u8> {
+        assert_eq!(zlib[..2], [0x78, 0x01]);
+        zlib = &zlib[2..];
+        let mut data = Vec::new();
+        loop {
+            let last = zlib[0] == 1;
+            let length = u16::from_le_bytes([zlib[1], zlib[2]]);
+            assert_eq!(!length, u16::from_le_bytes([zlib[3], zlib[4]]));
+            data.extend_from_slice(&zlib[5..5 + usize::from(length)]);
+            zlib = &zlib[5 + usize::from(length)..];
+            if last {
+                break;
+            }
+        }
+
+        assert_eq!(zlib, adler32_update(1, &data).to_be_bytes());
+        data
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[test]
+    fn writes_valid_png() {
+        let (width, height) = (200, 150);
+        let mut buffer = PixelBuffer::new(width, height, PixelFormatEnum::RGB24).unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                buffer.put_pixel(x, y, Color::RGB(x as u8, y as u8, (x ^ y) as u8));
+            }
+        }
+
+        let mut png = Vec::new();
+        write_png(&buffer, &mut png).unwrap();
+        let chunks = png_chunks(&png);
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds.first(), Some(&b"IHDR"));
+        assert_eq!(kinds.last(), Some(&b"IEND"));
+        assert!(kinds.iter().filter(|&&kind| kind == b"IDAT").count() > 1);
+        assert_eq!(chunks[0].1[..8], [0, 0, 0, 200, 0, 0, 0, 150]);
+
+        let zlib: Vec<u8> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"IDAT")
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect();
+        let expected: Vec<u8> = rgb_rows(&buffer)
+            .unwrap()
+            .flat_map(|row| [0].iter().chain(row).copied())
+            .collect();
+        assert_eq!(inflate_stored(&zlib), expected);
+    }
+
+    #[test]
+    fn rejects_other_pixel_formats() {
+        let buffer = PixelBuffer::new(2, 2, PixelFormatEnum::RGBA32).unwrap();
+        assert!(write_png(&buffer, &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use sdl2::rect::Point;
+
+use crate::{tile, Corner, Death, HudPanel, World, Wyrm};
+
+pub struct Selection {
+    pub wyrm_id: u16,
+    pub following: bool,
+    pub death: Option<Death>,
+}
+
+#[derive(Default)]
+pub struct Inspector {
+    pub selection: Option<Selection>,
+}
+
+fn color_hex(wyrm: &Wyrm) -> String {
+    let color = wyrm.color;
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn lineage(wyrm: &Wyrm) -> String {
+    match wyrm.parent {
+        Some(parent) => format!(
+            "child of #{parent}, gen {} of line #{}",
+            wyrm.generation, wyrm.founder
+        ),
+        None => "founder".to_string(),
+    }
+}
+
+impl Inspector {
+    // Selects the wyrm at `position`, or clears the selection if there is none.
+    pub fn select(&mut self, world: &World, position: Point) {
+        self.selection = match world.get_tile(position) {
+            Ok(wyrm_id) if wyrm_id >= tile::WYRM => Some(Selection {
+                wyrm_id,
+                following: false,
+                death: None,
+            }),
+            _ => None,
+        };
+    }
+
+    pub fn clear(&mut self) {
+        self.selection = None;
+    }
+
+    pub fn toggle_following(&mut self) {
+        if let Some(selection) = &mut self.selection {
+            selection.following = !selection.following;
+        }
+    }
+
+    pub fn record_deaths(&mut self, deaths: &[Death]) {
+        if let Some(selection) = &mut self.selection {
+            if selection.death.is_none() {
+                selection.death = deaths
+                    .iter()
+                    .find(|death| death.wyrm_id == selection.wyrm_id)
+                    .copied();
+                if selection.death.is_some() {
+                    selection.following = false;
+                }
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn selected_wyrm<'a>(&self, world: &'a World) -> Option<&'a Wyrm> {
+        let selection = self.selection.as_ref()?;
+        if selection.death.is_some() {
+            return None;
+        }
+
+        world.wyrms.get(&selection.wyrm_id)
+    }
+
+    #[must_use]
+    pub fn followed_position(&self, world: &World) -> Option<Point> {
+        let following = self.selection.as_ref()?.following;
+        let wyrm = self.selected_wyrm(world)?;
+        following.then(|| wyrm.head())
+    }
+
+    #[must_use]
+    pub fn panel(&self, world: &World) -> Option<HudPanel> {
+        let selection = self.selection.as_ref()?;
+        let id = selection.wyrm_id;
+
+        if let Some(death) = &selection.death {
+            let lines = vec![
+                format!("wyrm #{id}"),
+                format!("died at step {}", death.step),
+                death.cause.to_string(),
+                format!("length: {}", death.length),
+            ];
+            return Some(HudPanel::new(Corner::TopRight, lines));
+        }
+
+        let wyrm = self.selected_wyrm(world)?;
+        let age = world.current_step() - wyrm.born_at;
+        let heading = format!("{:?}", wyrm.direction).to_lowercase();
+        let following = if selection.following {
+            "following"
+        } else {
+            "press C to follow"
+        };
+        let lines = vec![
+            format!("wyrm #{id}"),
+            format!("length: {}", wyrm.size()),
+            format!("age: {age} steps"),
+            format!("heading: {heading}"),
+            format!("color: {}", color_hex(wyrm)),
+            format!("lineage: {}", lineage(wyrm)),
+            following.to_string(),
+        ];
+        Some(HudPanel {
+            corner: Corner::TopRight,
+            lines,
+            swatch: Some(wyrm.color),
+        })
+    }
+}
@@ -1,23 +1,70 @@
-#![cfg_attr(feature = "cargo-clippy", deny(clippy::all, clippy::pedantic))]
-#![cfg_attr(
-    feature = "cargo-clippy",
-    allow(
-        clippy::missing_docs_in_private_items,
-        clippy::missing_errors_doc,
-        clippy::missing_panics_doc
-    )
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(
+    clippy::missing_docs_in_private_items,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc
 )]
 
 mod app;
+mod brain;
+mod camera;
+pub mod cli;
+mod clock;
 mod color;
 pub mod config;
+mod damage;
 mod direction;
+pub mod font;
+mod game;
+mod gif;
+mod headless;
+mod heatmap;
+mod hud;
+mod image;
+mod inspector;
+mod map;
+mod pathfinder;
+mod pixels;
+mod player;
+mod renderer;
+mod screenshot;
+mod sensor;
+#[cfg(unix)]
+mod terminal;
+mod theme;
 mod tile;
+mod tiles;
+pub mod tool;
+mod video;
 mod world;
 mod wyrm;
 
 pub use app::App;
-pub use color::{random_wyrm_color, Color};
+pub use brain::{Brain, Forager};
+pub use camera::Camera;
+pub use clock::SimulationClock;
+pub use color::Color;
+pub use damage::{Damage, DamageTracker, DAMAGE_BLOCK_SIZE};
 pub use direction::{Direction, RelativeDirection};
-pub use world::{NewWorldParams, World};
-pub use wyrm::{NewWyrmParams, Wyrm};
+pub use game::{Game, GameMode, GameState, Player, VersusRule, KILL_SCORE};
+pub use gif::{GifEncoder, GifRecording, NewGifRecordingParams};
+pub use headless::{run_headless, HeadlessOutputs};
+pub use heatmap::{heat_color, Heatmap, HeatmapKind, HEATMAP_KINDS};
+pub use hud::{Corner, Hud, HudItem, HudPanel};
+pub use image::{save_image, write_png, write_ppm, ImageFormat};
+pub use inspector::{Inspector, Selection};
+pub use map::Map;
+pub use pathfinder::Pathfinder;
+pub use pixels::PixelBuffer;
+pub use player::{Controls, PlayerBrain};
+pub use renderer::{ImageRenderer, Renderer, SdlRenderer, TextRenderer};
+pub use screenshot::{capture, save_screenshot, timestamped_path, ScreenshotScale};
+pub use sensor::{SensedKind, SensorHit, SensorReading, SENSOR_DIRECTIONS};
+#[cfg(unix)]
+pub use terminal::run_terminal;
+pub use theme::{Theme, BUILTIN_THEMES};
+pub use tiles::{ChunkedTiles, DenseTiles, TileStorage, TileStore, CHUNK_SIZE};
+pub use tool::Tool;
+pub use video::{open_output, NewVideoStreamParams, VideoFormat, VideoStream, STDOUT_PATH};
+pub use world::{NewWorldParams, World, MAX_RECORDED_DEATHS};
+pub use wyrm::{Death, DeathCause, NewWyrmParams, Wyrm};
@@ -1,7 +1,70 @@
+use std::{env, process::ExitCode};
+
 use anyhow::Result;
-use wymrs::App;
+use wymrs::{
+    cli::{Args, Command, USAGE},
+    config::Config,
+    run_headless, App, HeadlessOutputs, Map,
+};
+
+#[cfg(unix)]
+use wymrs::run_terminal;
+
+fn setup(args: &Args) -> Result<(Config, Option<Map>)> {
+    Ok((args.config()?, args.map()?))
+}
+
+fn run(args: &Args, config: Config, map: Option<Map>) -> Result<()> {
+    if let Some(steps) = args.steps {
+        let outputs = HeadlessOutputs {
+            map_path: args.output_path.as_deref(),
+            screenshot_path: args.screenshot_path.as_deref(),
+            text_path: args.text_path.as_deref(),
+            gif_path: args.gif_path.as_deref(),
+            video_path: args.video_path.as_deref(),
+            video_format: args.video_format,
+            heatmap_path: args.heatmap_path.as_deref(),
+            heatmap_kind: args.heatmap_kind,
+        };
+        return run_headless(&config, map.as_ref(), steps, &outputs);
+    }
 
-pub fn main() -> Result<()> {
-    let mut app = App::new()?;
+    #[cfg(unix)]
+    if args.terminal {
+        return run_terminal(&config, map.as_ref());
+    }
+
+    let mut app = App::new(config, map.as_ref())?;
     app.run()
 }
+
+pub fn main() -> ExitCode {
+    let args = match Command::parse(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Run(args)) => args,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            eprintln!("Try `wymrs --help` for more information.");
+            return ExitCode::from(2);
+        }
+    };
+
+    let (config, map) = match setup(&args) {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args, config, map) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{fmt, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::tile;
+
+pub const WALL_CHAR: char = '#';
+pub const FOOD_CHAR: char = '*';
+pub const EMPTY_CHAR: char = '.';
+
+pub struct Map {
+    pub width: u16,
+    pub height: u16,
+    pub tiles: Vec<u16>,
+}
+
+impl Map {
+    pub fn parse(source: &str) -> Result<Self> {
+        let rows: Vec<&str> = source
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let height = rows.len();
+        if width < 3 || height < 3 {
+            bail!("map must be at least 3x3 tiles");
+        }
+
+        let mut tiles = vec![tile::EMPTY; width * height];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                tiles[y * width + x] = match c {
+                    WALL_CHAR => tile::WALL,
+                    FOOD_CHAR => tile::FOOD,
+                    EMPTY_CHAR | ' ' => tile::EMPTY,
+                    _ => bail!(
+                        "line {}, column {}: unexpected character {c:?}",
+                        y + 1,
+                        x + 1
+                    ),
+                };
+            }
+        }
+
+        Ok(Map {
+            width: u16::try_from(width).map_err(|_| anyhow!("map is too wide"))?,
+            height: u16::try_from(height).map_err(|_| anyhow!("map is too tall"))?,
+            tiles,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read map file {}", path.display()))?;
+        Map::parse(&source).with_context(|| format!("invalid map file {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())
+            .with_context(|| format!("failed to write map file {}", path.display()))
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.tiles.chunks(usize::from(self.width)) {
+            for &tile in row {
+                let c = match tile {
+                    tile::WALL => WALL_CHAR,
+                    tile::FOOD => FOOD_CHAR,
+                    _ => EMPTY_CHAR,
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let source = "#####\n#.*.#\n#####\n";
+        let map = Map::parse(source).unwrap();
+        assert_eq!((map.width, map.height), (5, 3));
+        assert_eq!(map.tiles[5], tile::WALL);
+        assert_eq!(map.tiles[6], tile::EMPTY);
+        assert_eq!(map.tiles[7], tile::FOOD);
+        assert_eq!(map.to_string(), source);
+    }
+
+    #[test]
+    fn pads_short_rows_and_skips_blank_lines() {
+        let map = Map::parse("###\n\n# \n#\n").unwrap();
+        assert_eq!((map.width, map.height), (3, 3));
+        assert_eq!(map.to_string(), "###\n#..\n#..\n");
+    }
+
+    #[test]
+    fn rejects_invalid_maps() {
+        assert!(Map::parse("##\n##\n").is_err());
+        let Err(err) = Map::parse("###\n#x#\n###\n") else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 2: unexpected character 'x'"
+        );
+    }
+}
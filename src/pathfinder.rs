@@ -0,0 +1,309 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use anyhow::Result;
+use sdl2::rect::Point;
+
+use crate::{tile, Brain, Direction, Forager, RelativeDirection, World, Wyrm};
+
+const CANDIDATE_COUNT: usize = 16;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+const RELATIVE_DIRECTIONS: [RelativeDirection; 4] = [
+    RelativeDirection::Forward,
+    RelativeDirection::Left,
+    RelativeDirection::Right,
+    RelativeDirection::Backward,
+];
+
+// Follows an A* path to the nearest reachable food, replanning whenever the
+// path is blocked or its target has been eaten. Finding food and searching for
+// a path share a budget of `budget` tiles per plan; plans that run out are
+// abandoned for that step in favor of `Forager`.
+pub struct Pathfinder {
+    budget: usize,
+    target: Option<Point>,
+    path: Vec<Point>,
+    fallback: Forager,
+}
+
+fn manhattan_distance(a: Point, b: Point) -> u32 {
+    (a.x() - b.x()).unsigned_abs() + (a.y() - b.y()).unsigned_abs()
+}
+
+fn is_passable(world: &World, position: Point) -> bool {
+    matches!(world.get_tile(position), Ok(tile::EMPTY | tile::FOOD))
+}
+
+fn relative_direction_to(wyrm: &Wyrm, destination: Point) -> Option<RelativeDirection> {
+    let head = wyrm.head();
+    RELATIVE_DIRECTIONS.into_iter().find(|&relative_direction| {
+        let direction = wyrm.direction.rotate(relative_direction);
+        head + direction.into() == destination
+    })
+}
+
+impl Pathfinder {
+    #[must_use]
+    pub fn new(budget: usize) -> Self {
+        Pathfinder {
+            budget,
+            target: None,
+            path: Vec::new(),
+            fallback: Forager,
+        }
+    }
+
+    fn path_is_valid(&self, world: &World) -> bool {
+        let Some(target) = self.target else {
+            return false;
+        };
+
+        matches!(world.get_tile(target), Ok(tile::FOOD))
+            && self
+                .path
+                .last()
+                .is_some_and(|&next| is_passable(world, next))
+    }
+
+    // The food closest to `start` by Manhattan distance: whole rings around
+    // it are scanned until `CANDIDATE_COUNT` are found. Every tile looked at
+    // is taken from `budget`.
+    fn find_candidates(world: &World, start: Point, budget: &mut usize) -> HashSet<Point> {
+        let mut candidates = HashSet::new();
+        let max_distance = i32::from(world.width) + i32::from(world.height);
+        for distance in 1..=max_distance {
+            for dx in -distance..=distance {
+                let dy = distance - dx.abs();
+                let ring = [start.offset(dx, dy), start.offset(dx, -dy)];
+                for position in &ring[..if dy == 0 { 1 } else { 2 }] {
+                    if *budget == 0 {
+                        return candidates;
+                    }
+                    *budget -= 1;
+
+                    if matches!(world.get_tile(*position), Ok(tile::FOOD)) {
+                        candidates.insert(*position);
+                    }
+                }
+            }
+
+            if candidates.len() >= CANDIDATE_COUNT {
+                break;
+            }
+        }
+
+        candidates
+    }
+
+    // A* towards the candidates, with the distance to the closest of them as
+    // the heuristic. The goal is reaching any candidate, so the heuristic never
+    // overestimates and the path leads to the candidate nearest by path.
+    fn plan(&mut self, world: &World, wyrm: &Wyrm) {
+        self.target = None;
+        self.path.clear();
+
+        let start = wyrm.head();
+        let behind = start + wyrm.direction.rotate(RelativeDirection::Backward).into();
+
+        let mut budget = self.budget;
+        let candidates = Pathfinder::find_candidates(world, start, &mut budget);
+        if candidates.is_empty() {
+            return;
+        }
+
+        let heuristic = |position: Point| {
+            candidates
+                .iter()
+                .map(|&candidate| manhattan_distance(position, candidate))
+                .min()
+                .unwrap_or(0)
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Point, Point> = HashMap::new();
+        let mut costs: HashMap<Point, u32> = HashMap::new();
+        open.push(Reverse((heuristic(start), 0, start.x(), start.y())));
+        costs.insert(start, 0);
+
+        while let Some(Reverse((_, cost, x, y))) = open.pop() {
+            let position = Point::new(x, y);
+            if costs.get(&position).is_some_and(|&best| best < cost) {
+                continue;
+            }
+
+            if candidates.contains(&position) {
+                self.target = Some(position);
+                self.path.push(position);
+                let mut current = position;
+                while let Some(&previous) = came_from.get(&current) {
+                    if previous == start {
+                        break;
+                    }
+                    self.path.push(previous);
+                    current = previous;
+                }
+                return;
+            }
+
+            if budget == 0 {
+                return;
+            }
+            budget -= 1;
+
+            for direction in DIRECTIONS {
+                let neighbor = position + direction.into();
+                if (position == start && neighbor == behind) || !is_passable(world, neighbor) {
+                    continue;
+                }
+
+                let neighbor_cost = cost + 1;
+                if costs
+                    .get(&neighbor)
+                    .is_some_and(|&best| best <= neighbor_cost)
+                {
+                    continue;
+                }
+
+                costs.insert(neighbor, neighbor_cost);
+                came_from.insert(neighbor, position);
+                let estimate = neighbor_cost + heuristic(neighbor);
+                open.push(Reverse((
+                    estimate,
+                    neighbor_cost,
+                    neighbor.x(),
+                    neighbor.y(),
+                )));
+            }
+        }
+    }
+}
+
+impl Brain for Pathfinder {
+    fn choose(&mut self, world: &World, wyrm: &Wyrm) -> Result<RelativeDirection> {
+        let head = wyrm.head();
+        let on_path = self
+            .path
+            .last()
+            .is_some_and(|&next| manhattan_distance(head, next) == 1);
+        if !on_path || !self.path_is_valid(world) {
+            self.plan(world, wyrm);
+        }
+
+        let next_step = self
+            .path
+            .pop()
+            .and_then(|next| relative_direction_to(wyrm, next));
+        match next_step {
+            Some(relative_direction) => Ok(relative_direction),
+            None => self.fallback.choose(world, wyrm),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::WorldConfig, Map};
+
+    // Food straight ahead of the wyrm, behind a wall it has to go around.
+    const MAP: &str = "\
+#########
+#...*...#
+#.......#
+#.#####.#
+#.......#
+#.......#
+#.......#
+#########
+";
+
+    fn world() -> World {
+        let config = WorldConfig {
+            spawn_interval: 1000,
+            poop_chance: 0.0,
+            seed: Some(1),
+            ..WorldConfig::default()
+        };
+        World::from_map(&Map::parse(MAP).unwrap(), config)
+    }
+
+    fn start() -> Point {
+        Point::new(4, 5)
+    }
+
+    #[test]
+    fn routes_around_walls() {
+        let mut world = world();
+        let id = world
+            .create_wyrm_from_segments(&[start()], Direction::Up)
+            .unwrap()
+            .unwrap();
+        let mut pathfinder = Pathfinder::new(4096);
+        pathfinder.plan(&world, world.get_wyrm(id).unwrap());
+
+        let food = Point::new(4, 1);
+        assert_eq!(pathfinder.target, Some(food));
+        // The path is stored back to front and leaves out the start.
+        assert_eq!(pathfinder.path.first(), Some(&food));
+        assert_eq!(pathfinder.path.len(), 10);
+        let mut previous = start();
+        for &position in pathfinder.path.iter().rev() {
+            assert_eq!(manhattan_distance(previous, position), 1);
+            assert!(is_passable(&world, position), "{position:?} is blocked");
+            previous = position;
+        }
+    }
+
+    #[test]
+    fn reaches_food() {
+        let mut world = world();
+        let brain = Box::new(Pathfinder::new(4096));
+        let id = world
+            .create_wyrm_with_brain(start(), brain)
+            .unwrap()
+            .unwrap();
+        for _ in 0..16 {
+            world.step().unwrap();
+            if world.get_wyrm(id).unwrap().food_eaten > 0 {
+                return;
+            }
+        }
+        panic!("the wyrm never reached the food");
+    }
+
+    #[test]
+    fn falls_back_when_out_of_budget() {
+        let mut world = world();
+        let id = world
+            .create_wyrm_from_segments(&[start()], Direction::Up)
+            .unwrap()
+            .unwrap();
+        let wyrm = world.get_wyrm(id).unwrap();
+
+        // Too small to find the food, then too small to find a path to it.
+        for budget in [3, 100] {
+            let mut pathfinder = Pathfinder::new(budget);
+            pathfinder.plan(&world, wyrm);
+            assert_eq!(pathfinder.target, None, "budget {budget}");
+            assert!(pathfinder.path.is_empty());
+            assert_eq!(
+                pathfinder.choose(&world, wyrm).unwrap(),
+                Forager.choose(&world, wyrm).unwrap()
+            );
+        }
+
+        let mut budget = 100;
+        let candidates = Pathfinder::find_candidates(&world, start(), &mut budget);
+        assert!(candidates.contains(&Point::new(4, 1)));
+        assert_eq!(budget, 0);
+    }
+}
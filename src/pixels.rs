@@ -0,0 +1,86 @@
+use anyhow::{bail, Result};
+use sdl2::{pixels::PixelFormatEnum, rect::Rect};
+
+use crate::Color;
+
+// An in-memory image in one of the byte-addressed SDL pixel formats, laid out
+// so it can be uploaded directly to a texture of the same format.
+pub struct PixelBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormatEnum,
+    pub pitch: usize,
+    pub data: Vec<u8>,
+}
+
+impl PixelBuffer {
+    pub fn new(width: u32, height: u32, format: PixelFormatEnum) -> Result<Self> {
+        if !matches!(
+            format,
+            PixelFormatEnum::RGB24
+                | PixelFormatEnum::BGR24
+                | PixelFormatEnum::RGBA32
+                | PixelFormatEnum::BGRA32
+        ) {
+            bail!("unsupported pixel format: {format:?}");
+        }
+
+        let pitch = format.byte_size_of_pixels(width.try_into()?);
+        let byte_size = format.byte_size_from_pitch_and_height(pitch, height.try_into()?);
+        Ok(PixelBuffer {
+            width,
+            height,
+            format,
+            pitch,
+            data: vec![0u8; byte_size],
+        })
+    }
+
+    #[must_use]
+    pub fn pixel_size(&self) -> usize {
+        self.format.byte_size_per_pixel()
+    }
+
+    #[must_use]
+    pub fn offset(&self, x: u32, y: u32) -> usize {
+        y as usize * self.pitch + x as usize * self.pixel_size()
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let i = self.offset(x, y);
+        let pixel = &mut self.data[i..i + self.format.byte_size_per_pixel()];
+        match self.format {
+            PixelFormatEnum::RGB24 => pixel.copy_from_slice(&[color.r, color.g, color.b]),
+            PixelFormatEnum::BGR24 => pixel.copy_from_slice(&[color.b, color.g, color.r]),
+            PixelFormatEnum::RGBA32 => {
+                pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+            PixelFormatEnum::BGRA32 => {
+                pixel.copy_from_slice(&[color.b, color.g, color.r, color.a]);
+            }
+            _ => unreachable!("unsupported pixel format"),
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let bounds = Rect::new(0, 0, self.width, self.height);
+        let Some(rect) = rect.intersection(bounds) else {
+            return;
+        };
+
+        for y in rect.top()..rect.bottom() {
+            for x in rect.left()..rect.right() {
+                self.put_pixel(x as u32, y as u32, color);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.fill_rect(Rect::new(0, 0, self.width, self.height), color);
+    }
+}
@@ -0,0 +1,66 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{Brain, Direction, RelativeDirection, World, Wyrm};
+
+// Turns pressed faster than the wyrm moves are kept for the following steps,
+// up to this many.
+const MAX_QUEUED_TURNS: usize = 2;
+
+// Headings requested from the keyboard, shared between the app and the brain
+// of the wyrm they steer.
+#[derive(Clone, Default)]
+pub struct Controls {
+    headings: Rc<RefCell<VecDeque<Direction>>>,
+}
+
+impl Controls {
+    pub fn steer(&self, direction: Direction) {
+        let mut headings = self.headings.borrow_mut();
+        if headings.len() < MAX_QUEUED_TURNS && headings.back() != Some(&direction) {
+            headings.push_back(direction);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.headings.borrow_mut().clear();
+    }
+
+    // The next queued turn away from `heading`. Requests to keep going or to
+    // reverse onto the wyrm's own body are skipped, as in classic snake.
+    fn next_turn(&self, heading: Direction) -> RelativeDirection {
+        let mut headings = self.headings.borrow_mut();
+        while let Some(direction) = headings.pop_front() {
+            let turn = heading.relative_to(direction);
+            if matches!(turn, RelativeDirection::Left | RelativeDirection::Right) {
+                return turn;
+            }
+        }
+
+        RelativeDirection::Forward
+    }
+}
+
+// Moves a wyrm as the keyboard says instead of deciding for itself.
+pub struct PlayerBrain {
+    controls: Controls,
+}
+
+impl PlayerBrain {
+    #[must_use]
+    pub fn new(controls: Controls) -> Self {
+        PlayerBrain { controls }
+    }
+}
+
+impl Brain for PlayerBrain {
+    fn choose(&mut self, _world: &World, wyrm: &Wyrm) -> Result<RelativeDirection> {
+        Ok(self.controls.next_turn(wyrm.direction))
+    }
+
+    // Players grow instead of splitting, so their length is their own.
+    fn can_reproduce(&self) -> bool {
+        false
+    }
+}
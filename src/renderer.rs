@@ -0,0 +1,214 @@
+use anyhow::Result;
+use sdl2::{
+    pixels::PixelFormatEnum,
+    rect::{Point, Rect},
+    render::{BlendMode, Canvas, Texture, TextureCreator},
+    video::{Window, WindowContext},
+};
+
+use crate::{tile, Color, Damage, PixelBuffer};
+
+// Receives a world tile by tile. `World::render` draws every tile between
+// `begin_frame` and `end_frame`; `World::render_changes` only the damaged ones.
+pub trait Renderer {
+    fn begin_frame(&mut self, _width: u16, _height: u16, _damage: &Damage) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw_tile(&mut self, position: Point, tile: u16, color: Color);
+
+    fn end_frame(&mut self, _damage: &Damage) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Draws into an in-memory image, each tile as a `scale` × `scale` block.
+pub struct ImageRenderer {
+    pub buffer: PixelBuffer,
+    scale: u32,
+}
+
+impl ImageRenderer {
+    pub fn new(format: PixelFormatEnum, scale: u32) -> Result<Self> {
+        Ok(ImageRenderer {
+            buffer: PixelBuffer::new(0, 0, format)?,
+            scale: scale.max(1),
+        })
+    }
+
+    #[must_use]
+    pub fn into_buffer(self) -> PixelBuffer {
+        self.buffer
+    }
+}
+
+impl Renderer for ImageRenderer {
+    fn begin_frame(&mut self, width: u16, height: u16, _damage: &Damage) -> Result<()> {
+        let size = (
+            u32::from(width) * self.scale,
+            u32::from(height) * self.scale,
+        );
+        if size != (self.buffer.width, self.buffer.height) {
+            self.buffer = PixelBuffer::new(size.0, size.1, self.buffer.format)?;
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn draw_tile(&mut self, position: Point, _tile: u16, color: Color) {
+        if self.scale == 1 {
+            self.buffer
+                .put_pixel(position.x() as u32, position.y() as u32, color);
+        } else {
+            let scale = self.scale.cast_signed();
+            let rect = Rect::new(
+                position.x() * scale,
+                position.y() * scale,
+                self.scale,
+                self.scale,
+            );
+            self.buffer.fill_rect(rect, color);
+        }
+    }
+}
+
+// Puts `texture` in `slot` and frees the texture it held. With
+// `unsafe_textures`, textures aren't freed when dropped.
+pub fn replace_texture(slot: &mut Texture, texture: Texture) {
+    let old = std::mem::replace(slot, texture);
+    // SAFETY: `old` was moved out of `slot`, so nothing else refers to it, and
+    // its renderer is alive, since textures are only replaced while drawing.
+    unsafe { old.destroy() };
+}
+
+// Draws into a streaming texture through a CPU-side buffer, uploading only the
+// damaged regions at the end of each frame.
+pub struct SdlRenderer {
+    texture_creator: TextureCreator<WindowContext>,
+    pub texture: Texture,
+    buffer: PixelBuffer,
+    // Blends the texture over what's below it with this opacity, for
+    // overlays.
+    alpha: Option<u8>,
+}
+
+impl SdlRenderer {
+    pub fn new(canvas: &Canvas<Window>, format: PixelFormatEnum) -> Result<Self> {
+        let texture_creator = canvas.texture_creator();
+        let buffer = PixelBuffer::new(1, 1, format)?;
+        let texture = texture_creator.create_texture_streaming(format, 1, 1)?;
+        Ok(SdlRenderer {
+            texture_creator,
+            texture,
+            buffer,
+            alpha: None,
+        })
+    }
+
+    // A renderer for overlays, whose texture is blended with `alpha` opacity.
+    // `format` needs an alpha channel.
+    pub fn overlay(canvas: &Canvas<Window>, format: PixelFormatEnum, alpha: u8) -> Result<Self> {
+        let mut renderer = SdlRenderer::new(canvas, format)?;
+        renderer.alpha = Some(alpha);
+        renderer.apply_blend();
+        Ok(renderer)
+    }
+
+    fn apply_blend(&mut self) {
+        if let Some(alpha) = self.alpha {
+            self.texture.set_blend_mode(BlendMode::Blend);
+            self.texture.set_alpha_mod(alpha);
+        }
+    }
+}
+
+impl Renderer for SdlRenderer {
+    fn begin_frame(&mut self, width: u16, height: u16, _damage: &Damage) -> Result<()> {
+        let size = (u32::from(width), u32::from(height));
+        if size != (self.buffer.width, self.buffer.height) {
+            self.buffer = PixelBuffer::new(size.0, size.1, self.buffer.format)?;
+            let texture = self.texture_creator.create_texture_streaming(
+                self.buffer.format,
+                size.0,
+                size.1,
+            )?;
+            replace_texture(&mut self.texture, texture);
+            self.apply_blend();
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn draw_tile(&mut self, position: Point, _tile: u16, color: Color) {
+        self.buffer
+            .put_pixel(position.x() as u32, position.y() as u32, color);
+    }
+
+    fn end_frame(&mut self, damage: &Damage) -> Result<()> {
+        match damage {
+            Damage::Full => {
+                self.texture
+                    .update(None, &self.buffer.data, self.buffer.pitch)?;
+            }
+            Damage::Partial { regions, .. } => {
+                for region in regions {
+                    #[allow(clippy::cast_sign_loss)]
+                    let offset = self.buffer.offset(region.x() as u32, region.y() as u32);
+                    self.texture
+                        .update(*region, &self.buffer.data[offset..], self.buffer.pitch)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Draws the world as characters, one per tile, in the style of map files.
+#[derive(Default)]
+pub struct TextRenderer {
+    width: usize,
+    cells: Vec<char>,
+}
+
+impl TextRenderer {
+    #[must_use]
+    pub fn tile_char(tile: u16) -> char {
+        match tile {
+            tile::EMPTY => '.',
+            tile::WALL => '#',
+            tile::FOOD => '*',
+            _ => 'o',
+        }
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().collect())
+            .collect()
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn begin_frame(&mut self, width: u16, height: u16, _damage: &Damage) -> Result<()> {
+        let size = usize::from(width) * usize::from(height);
+        if usize::from(width) != self.width || self.cells.len() != size {
+            self.width = width.into();
+            self.cells = vec![' '; size];
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn draw_tile(&mut self, position: Point, tile: u16, _color: Color) {
+        let i = position.y() as usize * self.width + position.x() as usize;
+        if let Some(cell) = self.cells.get_mut(i) {
+            *cell = TextRenderer::tile_char(tile);
+        }
+    }
+}
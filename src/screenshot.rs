@@ -0,0 +1,100 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Error, Result};
+
+use sdl2::pixels::PixelFormatEnum;
+
+use crate::{save_image, ImageFormat, ImageRenderer, PixelBuffer, World};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScreenshotScale {
+    // One pixel per tile.
+    Tile,
+    // Each tile is drawn `tile_size` pixels wide, as in the window.
+    TileSize,
+}
+
+impl FromStr for ScreenshotScale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tile" => Ok(ScreenshotScale::Tile),
+            "tile_size" => Ok(ScreenshotScale::TileSize),
+            _ => bail!("expected `tile` or `tile_size`"),
+        }
+    }
+}
+
+impl ScreenshotScale {
+    #[must_use]
+    pub fn factor(self, tile_size: u32) -> u32 {
+        match self {
+            ScreenshotScale::Tile => 1,
+            ScreenshotScale::TileSize => tile_size,
+        }
+    }
+}
+
+// Renders the whole world into a new RGB24 buffer, with every tile drawn as a
+// `scale` × `scale` block of pixels.
+pub fn capture(world: &World, scale: u32) -> Result<PixelBuffer> {
+    let mut renderer = ImageRenderer::new(PixelFormatEnum::RGB24, scale)?;
+    world.render(&mut renderer)?;
+    Ok(renderer.into_buffer())
+}
+
+pub fn save_screenshot(world: &World, scale: u32, path: &Path) -> Result<()> {
+    let format = ImageFormat::from_path(path)?;
+    save_image(&capture(world, scale)?, path, format)
+}
+
+// A path like `directory/wymrs-20240131-235959.png` for the current UTC time,
+// with a counter appended if that file already exists.
+#[must_use]
+pub fn timestamped_path(directory: &Path, extension: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let time = seconds % 86_400;
+    let stem = format!(
+        "wymrs-{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+
+    let mut path = directory.join(format!("{stem}.{extension}"));
+    let mut counter = 1;
+    while path.exists() {
+        path = directory.join(format!("{stem}-{counter}.{extension}"));
+        counter += 1;
+    }
+
+    path
+}
+
+// Converts days since 1970-01-01 into a proleptic Gregorian date, following
+// Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,212 @@
+use crate::{tile, RelativeDirection};
+
+pub const SENSOR_DIRECTIONS: [RelativeDirection; 3] = [
+    RelativeDirection::Forward,
+    RelativeDirection::Left,
+    RelativeDirection::Right,
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SensedKind {
+    Food,
+    Wall,
+    OwnBody,
+    Wyrm(u16),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SensorHit {
+    pub distance: u16,
+    pub kind: SensedKind,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SensorReading {
+    pub direction: RelativeDirection,
+    pub hit: Option<SensorHit>,
+}
+
+impl SensedKind {
+    #[must_use]
+    pub fn from_tile(tile: u16, wyrm_id: u16) -> Option<Self> {
+        match tile {
+            tile::EMPTY => None,
+            tile::WALL => Some(SensedKind::Wall),
+            tile::FOOD => Some(SensedKind::Food),
+            _ if tile == wyrm_id => Some(SensedKind::OwnBody),
+            other_id => Some(SensedKind::Wyrm(other_id)),
+        }
+    }
+
+    #[must_use]
+    pub fn is_obstacle(self) -> bool {
+        self != SensedKind::Food
+    }
+}
+
+impl SensorReading {
+    #[must_use]
+    pub fn food_distance(&self) -> Option<u16> {
+        self.hit
+            .filter(|hit| hit.kind == SensedKind::Food)
+            .map(|hit| hit.distance)
+    }
+
+    #[must_use]
+    pub fn obstacle_distance(&self) -> Option<u16> {
+        self.hit
+            .filter(|hit| hit.kind.is_obstacle())
+            .map(|hit| hit.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::rect::Point;
+
+    use super::*;
+    use crate::{config::WorldConfig, Direction, Map, World};
+
+    // Food two tiles north of the center and a wall right below it.
+    const MAP: &str = "\
+#########
+#.......#
+#...*...#
+#.......#
+#.......#
+#...#...#
+#.......#
+#.......#
+#########
+";
+
+    fn center() -> Point {
+        Point::new(4, 4)
+    }
+
+    fn world() -> World {
+        World::from_map(&Map::parse(MAP).unwrap(), WorldConfig::default())
+    }
+
+    fn hit(distance: u16, kind: SensedKind) -> SensorHit {
+        SensorHit { distance, kind }
+    }
+
+    fn hits(world: &World, wyrm_id: u16, range: u16) -> Vec<Option<SensorHit>> {
+        let readings = world.sense(wyrm_id, range).unwrap();
+        let directions: Vec<RelativeDirection> =
+            readings.iter().map(|reading| reading.direction).collect();
+        assert_eq!(directions, SENSOR_DIRECTIONS);
+        readings.iter().map(|reading| reading.hit).collect()
+    }
+
+    #[test]
+    fn senses_relative_to_heading() {
+        let cases = [
+            (
+                Direction::Up,
+                [
+                    Some(hit(2, SensedKind::Food)),
+                    Some(hit(4, SensedKind::Wall)),
+                    Some(hit(4, SensedKind::Wall)),
+                ],
+            ),
+            (
+                Direction::Right,
+                [
+                    Some(hit(4, SensedKind::Wall)),
+                    Some(hit(2, SensedKind::Food)),
+                    Some(hit(1, SensedKind::Wall)),
+                ],
+            ),
+            (
+                Direction::Left,
+                [
+                    Some(hit(4, SensedKind::Wall)),
+                    Some(hit(1, SensedKind::Wall)),
+                    Some(hit(2, SensedKind::Food)),
+                ],
+            ),
+        ];
+        for (direction, expected) in cases {
+            let mut world = world();
+            let id = world
+                .create_wyrm_from_segments(&[center()], direction)
+                .unwrap()
+                .unwrap();
+            assert_eq!(hits(&world, id, 16), expected, "heading {direction:?}");
+        }
+    }
+
+    #[test]
+    fn senses_own_body_and_other_wyrms() {
+        let mut world = world();
+        let segments = [
+            center(),
+            Point::new(4, 5),
+            Point::new(5, 5),
+            Point::new(5, 4),
+        ];
+        world.set_tile(Point::new(4, 5), tile::EMPTY);
+        let id = world
+            .create_wyrm_from_segments(&segments, Direction::Up)
+            .unwrap()
+            .unwrap();
+        let other_id = world
+            .create_wyrm_from_segments(&[Point::new(2, 4)], Direction::Up)
+            .unwrap()
+            .unwrap();
+
+        let readings = hits(&world, id, 16);
+        assert_eq!(
+            readings,
+            [
+                Some(hit(2, SensedKind::Food)),
+                Some(hit(2, SensedKind::Wyrm(other_id))),
+                Some(hit(1, SensedKind::OwnBody)),
+            ]
+        );
+    }
+
+    #[test]
+    fn range_cuts_rays_short() {
+        let mut world = world();
+        let id = world
+            .create_wyrm_from_segments(&[center()], Direction::Right)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            hits(&world, id, 3),
+            [
+                None,
+                Some(hit(2, SensedKind::Food)),
+                Some(hit(1, SensedKind::Wall))
+            ]
+        );
+        assert_eq!(
+            hits(&world, id, 1),
+            [None, None, Some(hit(1, SensedKind::Wall))]
+        );
+        assert_eq!(world.cast_ray(id, center(), Direction::Down, 0), None);
+    }
+
+    #[test]
+    fn readings_split_food_from_obstacles() {
+        let food = SensorReading {
+            direction: RelativeDirection::Forward,
+            hit: Some(hit(3, SensedKind::Food)),
+        };
+        assert_eq!(food.food_distance(), Some(3));
+        assert_eq!(food.obstacle_distance(), None);
+
+        let wall = SensorReading {
+            direction: RelativeDirection::Left,
+            hit: Some(hit(1, SensedKind::Wall)),
+        };
+        assert_eq!(wall.food_distance(), None);
+        assert_eq!(wall.obstacle_distance(), Some(1));
+        assert_eq!(SensedKind::from_tile(tile::EMPTY, 7), None);
+        assert_eq!(SensedKind::from_tile(7, 7), Some(SensedKind::OwnBody));
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    mem, thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    config::{Config, PIXEL_FORMAT},
+    Color, ImageRenderer, Map, PixelBuffer, SimulationClock, World,
+};
+
+const FRAME_TIME: Duration = Duration::from_millis(33);
+const MIN_SPEED_LEVEL: i32 = -3;
+const MAX_SPEED_LEVEL: i32 = 4;
+const PAN_STEP: i32 = 4;
+const STATUS_LINES: u16 = 1;
+const HALF_BLOCK: char = '▀';
+
+// Puts the terminal into raw, non-blocking mode for as long as it lives, so
+// single key presses can be read without waiting for a newline.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        // SAFETY: `termios` is plain old data, and both calls only access the
+        // struct they're given.
+        unsafe {
+            let mut original: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &raw mut original) != 0 {
+                bail!("stdin is not a terminal: {}", io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            libc::cfmakeraw(&raw mut raw);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw const raw) != 0 {
+                bail!(
+                    "failed to switch the terminal to raw mode: {}",
+                    io::Error::last_os_error()
+                );
+            }
+
+            Ok(RawMode { original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw const self.original);
+        }
+    }
+}
+
+// Columns and rows of the terminal on stdout.
+fn terminal_size() -> Result<(u16, u16)> {
+    // SAFETY: `winsize` is plain old data filled in by the ioctl.
+    unsafe {
+        let mut size: libc::winsize = mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &raw mut size) != 0
+            || size.ws_col == 0
+        {
+            bail!(
+                "failed to get the terminal size: {}",
+                io::Error::last_os_error()
+            );
+        }
+
+        Ok((size.ws_col, size.ws_row))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Key {
+    Quit,
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    Pan(i32, i32),
+    Center,
+}
+
+fn parse_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        let key = match input[i] {
+            b'q' | b'Q' | 3 => Some(Key::Quit),
+            b' ' => Some(Key::Pause),
+            b'.' => Some(Key::Step),
+            b'+' | b'=' => Some(Key::Faster),
+            b'-' => Some(Key::Slower),
+            b'h' => Some(Key::Pan(-PAN_STEP, 0)),
+            b'j' => Some(Key::Pan(0, PAN_STEP)),
+            b'k' => Some(Key::Pan(0, -PAN_STEP)),
+            b'l' => Some(Key::Pan(PAN_STEP, 0)),
+            b'c' => Some(Key::Center),
+            0x1b if input.get(i + 1) == Some(&b'[') => {
+                i += 2;
+                match input.get(i) {
+                    Some(b'A') => Some(Key::Pan(0, -PAN_STEP)),
+                    Some(b'B') => Some(Key::Pan(0, PAN_STEP)),
+                    Some(b'C') => Some(Key::Pan(PAN_STEP, 0)),
+                    Some(b'D') => Some(Key::Pan(-PAN_STEP, 0)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        keys.extend(key);
+        i += 1;
+    }
+
+    keys
+}
+
+// Draws the world into the terminal, two tiles per character cell: the upper
+// tile is the foreground of a `▀`, the lower one its background. Only cells
+// that changed since the last frame are rewritten.
+struct TerminalView {
+    size: (u16, u16),
+    columns: u16,
+    rows: u16,
+    offset: (i32, i32),
+    cells: Vec<Option<(Color, Color)>>,
+    output: String,
+}
+
+impl TerminalView {
+    fn new((columns, terminal_rows): (u16, u16)) -> Self {
+        let rows = terminal_rows.saturating_sub(STATUS_LINES).max(1);
+        TerminalView {
+            size: (columns, terminal_rows),
+            columns,
+            rows,
+            offset: (0, 0),
+            cells: vec![None; usize::from(columns) * usize::from(rows)],
+            output: String::new(),
+        }
+    }
+
+    fn viewport_tiles(&self) -> (i32, i32) {
+        (i32::from(self.columns), i32::from(self.rows) * 2)
+    }
+
+    fn center_on(&mut self, world: &World) {
+        let (width, height) = self.viewport_tiles();
+        self.offset = (
+            (i32::from(world.width) - width) / 2,
+            (i32::from(world.height) - height) / 2,
+        );
+        self.clamp_offset(world);
+    }
+
+    fn pan(&mut self, world: &World, dx: i32, dy: i32) {
+        self.offset.0 += dx;
+        self.offset.1 += dy;
+        self.clamp_offset(world);
+    }
+
+    // Keeps the world on screen; worlds smaller than the terminal stay centered.
+    fn clamp_offset(&mut self, world: &World) {
+        let (width, height) = self.viewport_tiles();
+        let clamp_axis = |offset: i32, size: i32, view: i32| {
+            if size <= view {
+                (size - view) / 2
+            } else {
+                offset.clamp(0, size - view)
+            }
+        };
+        self.offset.0 = clamp_axis(self.offset.0, i32::from(world.width), width);
+        self.offset.1 = clamp_axis(self.offset.1, i32::from(world.height), height);
+    }
+
+    fn invalidate(&mut self) {
+        self.cells.fill(None);
+        self.output.push_str("\x1b[0m\x1b[2J");
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn tile_color(pixels: &PixelBuffer, x: i32, y: i32) -> Color {
+        if x < 0 || y < 0 || x >= pixels.width.cast_signed() || y >= pixels.height.cast_signed() {
+            return Color::BLACK;
+        }
+
+        let i = pixels.offset(x as u32, y as u32);
+        Color::RGB(pixels.data[i], pixels.data[i + 1], pixels.data[i + 2])
+    }
+
+    fn draw(&mut self, pixels: &PixelBuffer, status: &str) -> Result<String> {
+        let mut current_colors: Option<(Color, Color)> = None;
+        let mut cursor: Option<(u16, u16)> = None;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let x = self.offset.0 + i32::from(column);
+                let y = self.offset.1 + i32::from(row) * 2;
+                let colors = (
+                    TerminalView::tile_color(pixels, x, y),
+                    TerminalView::tile_color(pixels, x, y + 1),
+                );
+
+                let cell = &mut self.cells
+                    [usize::from(row) * usize::from(self.columns) + usize::from(column)];
+                if *cell == Some(colors) {
+                    continue;
+                }
+                *cell = Some(colors);
+
+                if cursor != Some((row, column)) {
+                    write!(self.output, "\x1b[{};{}H", row + 1, column + 1)?;
+                }
+                if current_colors != Some(colors) {
+                    let (top, bottom) = colors;
+                    write!(
+                        self.output,
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+                    )?;
+                    current_colors = Some(colors);
+                }
+                self.output.push(HALF_BLOCK);
+                cursor = Some((row, column + 1));
+            }
+        }
+
+        let status: String = status.chars().take(usize::from(self.columns)).collect();
+        write!(
+            self.output,
+            "\x1b[0m\x1b[{};1H\x1b[2K{status}",
+            self.rows + 1
+        )?;
+        Ok(mem::take(&mut self.output))
+    }
+}
+
+struct TerminalApp {
+    config: Config,
+    world: World,
+    view: TerminalView,
+    renderer: ImageRenderer,
+    clock: SimulationClock,
+    paused: bool,
+    speed_level: i32,
+}
+
+impl TerminalApp {
+    fn speed(&self) -> f64 {
+        2f64.powi(self.speed_level)
+    }
+
+    fn set_speed_level(&mut self, speed_level: i32) {
+        self.speed_level = speed_level.clamp(MIN_SPEED_LEVEL, MAX_SPEED_LEVEL);
+        let step_time = self.config.step_time().div_f64(self.speed());
+        self.clock.set_step_time(step_time);
+    }
+
+    // Returns false once the user asked to quit.
+    fn handle_key(&mut self, key: Key) -> Result<bool> {
+        match key {
+            Key::Quit => return Ok(false),
+            Key::Pause => {
+                self.paused = !self.paused;
+                self.clock.reset();
+            }
+            Key::Step if self.paused => {
+                self.world.step()?;
+                self.clock.record_steps(1);
+            }
+            Key::Step => {}
+            Key::Faster => self.set_speed_level(self.speed_level + 1),
+            Key::Slower => self.set_speed_level(self.speed_level - 1),
+            Key::Pan(dx, dy) => self.view.pan(&self.world, dx, dy),
+            Key::Center => self.view.center_on(&self.world),
+        }
+
+        Ok(true)
+    }
+
+    fn status(&self) -> String {
+        let speed = if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{}x", self.speed())
+        };
+        format!(
+            "step {} | {} wyrms | {speed} | {:.1} steps/s | q quit, space pause, +/- speed, arrows pan",
+            self.world.current_step(),
+            self.world.wyrms.len(),
+            self.clock.steps_per_second()
+        )
+    }
+
+    fn frame(&mut self, out: &mut impl Write) -> Result<()> {
+        let size = terminal_size()?;
+        if size != self.view.size {
+            let offset = self.view.offset;
+            self.view = TerminalView::new(size);
+            self.view.offset = offset;
+            self.view.clamp_offset(&self.world);
+            self.view.invalidate();
+        }
+
+        if !self.paused {
+            let steps = self.clock.tick();
+            for _ in 0..steps {
+                self.world.step()?;
+            }
+            self.clock.record_steps(steps);
+        }
+
+        self.world.render_changes(&mut self.renderer)?;
+        let status = self.status();
+        out.write_all(self.view.draw(&self.renderer.buffer, &status)?.as_bytes())?;
+        out.flush()?;
+        self.clock.record_frame();
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<()> {
+        let mut stdin = io::stdin();
+        let mut stdout = io::stdout().lock();
+        let mut input = [0u8; 64];
+        self.view.invalidate();
+
+        loop {
+            let read = stdin.read(&mut input)?;
+            for key in parse_keys(&input[..read]) {
+                if !self.handle_key(key)? {
+                    return Ok(());
+                }
+            }
+
+            self.frame(&mut stdout)?;
+            thread::sleep(FRAME_TIME);
+        }
+    }
+}
+
+// Runs the simulation in the terminal instead of an SDL window. Without an
+// explicit world size, the world fills the terminal.
+pub fn run_terminal(config: &Config, map: Option<&Map>) -> Result<()> {
+    let (columns, rows) = terminal_size()?;
+    let mut config = config.clone();
+    // The terminal can't show heatmaps, so don't count them.
+    config.world.heatmaps = false;
+    if map.is_none() {
+        let tile_rows = rows.saturating_sub(STATUS_LINES).saturating_mul(2);
+        config.world_width.get_or_insert(columns.max(3));
+        config.world_height.get_or_insert(tile_rows.max(3));
+    }
+
+    let world = World::from_config(&config, map)?;
+    let renderer = ImageRenderer::new(PIXEL_FORMAT, 1)?;
+    let clock = SimulationClock::new(config.step_time(), config.max_catch_up_steps);
+    let mut view = TerminalView::new((columns, rows));
+    view.center_on(&world);
+
+    let mut app = TerminalApp {
+        config,
+        world,
+        view,
+        renderer,
+        clock,
+        paused: false,
+        speed_level: 0,
+    };
+
+    let raw_mode = RawMode::enable()?;
+    let mut stdout = io::stdout();
+    // Switch to the alternate screen and hide the cursor while running.
+    write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+    let result = app.run();
+    write!(stdout, "\x1b[0m\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    drop(raw_mode);
+    result
+}
@@ -0,0 +1,370 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    color::{self, LchDistributionValues, NormalDistributionValues},
+    config, tile, Color,
+};
+
+pub const BUILTIN_THEMES: [&str; 3] = ["default", "colorblind", "high_contrast"];
+
+// How often a wyrm color is desaturated to bring its lightness back in range
+// after sRGB gamut clipping, before falling back to gray.
+const MAX_CONTRAST_ATTEMPTS: u32 = 4;
+const CONTRAST_EPSILON: f32 = 1e-3;
+
+// The colors the world is drawn with. Wyrm colors are sampled from one of
+// `wyrm_colors`, picked by wyrm ID, and kept at least `min_contrast` Oklch
+// lightness away from both food and the background.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub empty: Color,
+    pub wall: Color,
+    pub food: Color,
+    pub missing: Color,
+    pub wyrm_colors: Vec<LchDistributionValues>,
+    pub min_contrast: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        // common
+        const ORANGEYELLOW: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (90.0, 10.0));
+        const YELLOWGREEN: LchDistributionValues = ((0.85, 0.05), (0.3, 0.05), (120.0, 5.0));
+        const GREENGREEN: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (145.0, 5.0));
+        const BLUEGREEN: LchDistributionValues = ((0.8, 0.05), (0.25, 0.05), (160.0, 5.0));
+        const CREAM: LchDistributionValues = ((0.85, 0.05), (0.1, 0.05), (80.0, 10.0));
+
+        // exotic
+        const LIGHTBLUE: LchDistributionValues = ((0.85, 0.05), (0.2, 0.05), (220.0, 5.0));
+        const FUCHSIA: LchDistributionValues = ((0.67, 0.05), (0.3, 0.02), (10.0, 10.0));
+
+        Theme {
+            name: "default".to_string(),
+            empty: color::color_from_oklch(0.18, 0.05, 250.0),
+            wall: color::color_from_oklch(0.6, 0.09, 300.0),
+            food: color::color_from_oklch(0.4, 0.15, 20.0),
+            missing: color::color_from_oklch(1.0, 0.0, 0.0),
+            wyrm_colors: vec![
+                ORANGEYELLOW,
+                ORANGEYELLOW,
+                YELLOWGREEN,
+                YELLOWGREEN,
+                YELLOWGREEN,
+                GREENGREEN,
+                GREENGREEN,
+                GREENGREEN,
+                BLUEGREEN,
+                BLUEGREEN,
+                BLUEGREEN,
+                CREAM,
+                CREAM,
+                LIGHTBLUE,
+                FUCHSIA,
+            ],
+            min_contrast: 0.0,
+        }
+    }
+}
+
+impl Theme {
+    // Avoids telling colors apart by red versus green: wyrms use the blue,
+    // orange and purple hues of the Okabe-Ito palette at mid lightness, while
+    // food is a much lighter yellow on a dark background.
+    #[must_use]
+    pub fn colorblind() -> Self {
+        Theme {
+            name: "colorblind".to_string(),
+            empty: color::color_from_oklch(0.2, 0.02, 250.0),
+            wall: color::color_from_oklch(0.45, 0.0, 0.0),
+            food: color::color_from_oklch(0.93, 0.17, 105.0),
+            missing: color::color_from_oklch(1.0, 0.0, 0.0),
+            wyrm_colors: vec![
+                ((0.62, 0.03), (0.14, 0.02), (250.0, 8.0)),
+                ((0.72, 0.03), (0.15, 0.02), (60.0, 8.0)),
+                ((0.7, 0.03), (0.1, 0.02), (230.0, 8.0)),
+                ((0.62, 0.03), (0.12, 0.02), (345.0, 8.0)),
+            ],
+            min_contrast: 0.15,
+        }
+    }
+
+    // Saturated mid-lightness wyrms and bright food on a black background.
+    #[must_use]
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "high_contrast".to_string(),
+            empty: Color::BLACK,
+            wall: color::color_from_oklch(0.4, 0.0, 0.0),
+            food: Color::WHITE,
+            missing: color::color_from_oklch(0.7, 0.25, 30.0),
+            wyrm_colors: vec![
+                ((0.62, 0.02), (0.2, 0.03), (30.0, 10.0)),
+                ((0.62, 0.02), (0.15, 0.03), (145.0, 10.0)),
+                ((0.62, 0.02), (0.18, 0.03), (260.0, 10.0)),
+                ((0.62, 0.02), (0.15, 0.03), (90.0, 10.0)),
+            ],
+            min_contrast: 0.3,
+        }
+    }
+
+    #[must_use]
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::default()),
+            "colorblind" => Some(Theme::colorblind()),
+            "high_contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    // Resolves a `theme` setting: either a built-in theme name or the path of
+    // a theme file.
+    pub fn from_setting(value: &str) -> Result<Self> {
+        match Theme::builtin(value) {
+            Some(theme) => Ok(theme),
+            None => Theme::load(Path::new(value)),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut theme = Theme::default();
+        let mut wyrm_colors = Vec::new();
+        config::parse_lines(source, |key, value| {
+            match key {
+                "name" => theme.name = config::parse_string(value),
+                "empty" => theme.empty = config::parse_color(key, value)?,
+                "wall" => theme.wall = config::parse_color(key, value)?,
+                "food" => theme.food = config::parse_color(key, value)?,
+                "missing" => theme.missing = config::parse_color(key, value)?,
+                "min_contrast" => theme.min_contrast = config::parse_value(key, value)?,
+                "wyrm" => wyrm_colors.push(parse_distribution(value)?),
+                _ => bail!("unknown key `{key}`"),
+            }
+            Ok(())
+        })?;
+
+        if !wyrm_colors.is_empty() {
+            theme.wyrm_colors = wyrm_colors;
+        }
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read theme file {}", path.display()))?;
+        Theme::parse(&source).with_context(|| format!("invalid theme file {}", path.display()))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.wyrm_colors.is_empty() {
+            bail!("a theme needs at least one `wyrm` color");
+        }
+        if !(0.0..=0.5).contains(&self.min_contrast) {
+            bail!("`min_contrast` must be between 0 and 0.5");
+        }
+
+        let empty = color::oklch_from_color(self.empty).l;
+        let food = color::oklch_from_color(self.food).l;
+        if (empty - food).abs() + CONTRAST_EPSILON < self.min_contrast {
+            bail!(
+                "food and background lightness differ by {:.2}, less than `min_contrast`",
+                (empty - food).abs()
+            );
+        }
+        if self.allowed_lightness(0.5).is_none() {
+            bail!("`min_contrast` leaves no lightness for wyrms");
+        }
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn tile_color(&self, tile: u16) -> Color {
+        match tile {
+            tile::EMPTY => self.empty,
+            tile::WALL => self.wall,
+            tile::FOOD => self.food,
+            _ => self.missing,
+        }
+    }
+
+    // The lightness closest to `l` that keeps `min_contrast` away from the
+    // background and food, if there is one.
+    fn allowed_lightness(&self, l: f32) -> Option<f32> {
+        let anchors = [
+            color::oklch_from_color(self.empty).l,
+            color::oklch_from_color(self.food).l,
+        ];
+        let contrast = self.min_contrast;
+        let allowed = |candidate: f32| {
+            anchors
+                .iter()
+                .all(|anchor| (candidate - anchor).abs() + CONTRAST_EPSILON >= contrast)
+        };
+
+        let mut candidates = vec![l.clamp(0.0, 1.0), 0.0, 1.0];
+        for anchor in anchors {
+            candidates.push((anchor - contrast).clamp(0.0, 1.0));
+            candidates.push((anchor + contrast).clamp(0.0, 1.0));
+        }
+        candidates
+            .into_iter()
+            .filter(|&candidate| allowed(candidate))
+            .min_by(|a, b| (a - l).abs().total_cmp(&(b - l).abs()))
+    }
+
+    fn has_contrast(&self, color: Color) -> bool {
+        let l = color::oklch_from_color(color).l;
+        self.allowed_lightness(l)
+            .is_some_and(|allowed| (allowed - l).abs() < CONTRAST_EPSILON)
+    }
+
+    // Moves a wyrm color's lightness out of the bands around the background
+    // and food lightness. Saturated colors can change lightness when clipped
+    // to sRGB, so chroma is reduced until the result really has the contrast.
+    #[must_use]
+    pub fn constrain(&self, color: Color) -> Color {
+        if self.min_contrast <= 0.0 || self.has_contrast(color) {
+            return color;
+        }
+
+        let lch = color::oklch_from_color(color);
+        let Some(l) = self.allowed_lightness(lch.l) else {
+            return color;
+        };
+
+        let mut chroma = lch.chroma;
+        for _ in 0..MAX_CONTRAST_ATTEMPTS {
+            let candidate = color::color_from_oklch(l, chroma, lch.hue.into_degrees());
+            if self.has_contrast(candidate) {
+                return candidate;
+            }
+            chroma /= 2.0;
+        }
+
+        color::color_from_oklch(l, 0.0, 0.0)
+    }
+
+    pub fn random_wyrm_color<R: Rng>(&self, rng: &mut R, id: u16) -> Result<Color> {
+        let n: i32 = self.wyrm_colors.len().try_into()?;
+
+        let offset: i32 = rng.gen_range(-1..=3);
+        let base_index = i32::from(id - tile::WYRM);
+        let raw_index = (base_index + offset).rem_euclid(n);
+        let index = usize::try_from(raw_index)?;
+
+        let color = color::random_color(rng, self.wyrm_colors[index])?;
+        Ok(self.constrain(color))
+    }
+
+    // A color for an existing wyrm when switching themes, derived from its ID
+    // alone so the simulation's random numbers are left untouched.
+    pub fn recolor_wyrm(&self, id: u16) -> Result<Color> {
+        let mut rng = StdRng::seed_from_u64(u64::from(id));
+        self.random_wyrm_color(&mut rng, id)
+    }
+}
+
+// Parses `l l_dev, chroma chroma_dev, hue hue_dev`: the mean and standard
+// deviation of each Oklch component.
+fn parse_distribution(value: &str) -> Result<LchDistributionValues> {
+    let invalid = || {
+        anyhow!(
+            "invalid value for `wyrm`: {value:?} (expected `l l_dev, chroma chroma_dev, hue hue_dev`)"
+        )
+    };
+    let components = value
+        .split(',')
+        .map(|component| {
+            let mut numbers = component.split_whitespace().map(str::parse::<f32>);
+            match (numbers.next(), numbers.next(), numbers.next()) {
+                (Some(Ok(mean)), Some(Ok(std_dev)), None) if std_dev >= 0.0 => Ok((mean, std_dev)),
+                _ => Err(invalid()),
+            }
+        })
+        .collect::<Result<Vec<NormalDistributionValues>>>()?;
+
+    match components[..] {
+        [l, chroma, hue] => Ok((l, chroma, hue)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The theme file example in the README.
+    fn readme_example() -> &'static str {
+        let readme = include_str!("../README.md");
+        let start = readme.find("```\nname = ").unwrap() + 4;
+        let end = start + readme[start..].find("```").unwrap();
+        &readme[start..end]
+    }
+
+    #[test]
+    fn parses_readme_example() {
+        let theme = Theme::parse(readme_example()).unwrap();
+        assert_eq!(theme.name, "dusk");
+        assert_eq!(theme.empty, Color::RGB(0x14, 0x16, 0x1f));
+        assert_eq!(theme.food, Color::RGB(0xf2, 0xe3, 0x94));
+        assert!((theme.min_contrast - 0.2).abs() < f32::EPSILON);
+        assert_eq!(
+            theme.wyrm_colors,
+            vec![
+                ((0.6, 0.03), (0.15, 0.03), (30.0, 10.0)),
+                ((0.65, 0.03), (0.12, 0.03), (250.0, 10.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_defaults_for_missing_keys() {
+        let theme = Theme::parse("# just a comment\nwall = #ffffff\n").unwrap();
+        let default = Theme::default();
+        assert_eq!(theme.wall, Color::RGB(255, 255, 255));
+        assert_eq!(theme.empty, default.empty);
+        assert_eq!(theme.wyrm_colors, default.wyrm_colors);
+    }
+
+    #[test]
+    fn allows_comments_like_config_files() {
+        let theme = Theme::parse(
+            "# dusk\nname = \"dusk # 2\"  # quoted\nwall = #ffffff   # white\nmin_contrast = 0.1 # low\n",
+        )
+        .unwrap();
+        assert_eq!(theme.name, "dusk # 2");
+        assert_eq!(theme.wall, Color::RGB(255, 255, 255));
+        assert!((theme.min_contrast - 0.1).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let err = Theme::parse("name = x\nshade = #ffffff\n").unwrap_err();
+        assert_eq!(format!("{err:#}"), "line 2: unknown key `shade`");
+
+        let err = Theme::parse("food = yellow\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1");
+    }
+
+    #[test]
+    fn rejects_invalid_distributions() {
+        assert!(Theme::parse("wyrm = 0.6 0.03, 0.15 0.03").is_err());
+        assert!(Theme::parse("wyrm = 0.6 0.03, 0.15 0.03, 30").is_err());
+        assert!(Theme::parse("wyrm = 0.6 -0.03, 0.15 0.03, 30 10").is_err());
+    }
+
+    #[test]
+    fn validates_contrast() {
+        assert!(Theme::parse("min_contrast = 0.6").is_err());
+        assert!(Theme::parse("empty = #808080\nfood = #808080\nmin_contrast = 0.1").is_err());
+        for name in BUILTIN_THEMES {
+            Theme::builtin(name).unwrap().validate().unwrap();
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use sdl2::rect::Point;
+
+use crate::tile;
+
+pub const CHUNK_SIZE: i32 = 64;
+
+#[allow(clippy::cast_sign_loss)]
+pub const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TileStorage {
+    Dense,
+    Chunked,
+}
+
+pub enum TileStore {
+    Dense(DenseTiles),
+    Chunked(ChunkedTiles),
+}
+
+pub struct DenseTiles {
+    width: u16,
+    height: u16,
+    tiles: Vec<u16>,
+}
+
+struct Chunk {
+    tiles: Box<[u16]>,
+    occupied: usize,
+}
+
+// Tiles are stored in square chunks that are only allocated once they contain
+// a non-empty tile, and are unloaded again as soon as they are empty.
+#[derive(Default)]
+pub struct ChunkedTiles {
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl TileStore {
+    #[must_use]
+    pub fn new(storage: TileStorage, width: u16, height: u16) -> Self {
+        match storage {
+            TileStorage::Dense => TileStore::Dense(DenseTiles::new(width, height)),
+            TileStorage::Chunked => TileStore::Chunked(ChunkedTiles::default()),
+        }
+    }
+
+    #[must_use]
+    pub fn storage(&self) -> TileStorage {
+        match self {
+            TileStore::Dense(_) => TileStorage::Dense,
+            TileStore::Chunked(_) => TileStorage::Chunked,
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, position: Point) -> Option<u16> {
+        match self {
+            TileStore::Dense(tiles) => tiles.get(position),
+            TileStore::Chunked(tiles) => Some(tiles.get(position)),
+        }
+    }
+
+    pub fn set(&mut self, position: Point, tile: u16) {
+        match self {
+            TileStore::Dense(tiles) => tiles.set(position, tile),
+            TileStore::Chunked(tiles) => tiles.set(position, tile),
+        }
+    }
+}
+
+impl DenseTiles {
+    #[must_use]
+    pub fn new(width: u16, height: u16) -> Self {
+        DenseTiles {
+            width,
+            height,
+            tiles: vec![tile::EMPTY; usize::from(width) * usize::from(height)],
+        }
+    }
+
+    fn index(&self, position: Point) -> Option<usize> {
+        let x = usize::try_from(position.x()).ok()?;
+        let y = usize::try_from(position.y()).ok()?;
+        let (width, height) = (usize::from(self.width), usize::from(self.height));
+        (x < width && y < height).then_some(y * width + x)
+    }
+
+    #[must_use]
+    pub fn get(&self, position: Point) -> Option<u16> {
+        self.index(position).map(|index| self.tiles[index])
+    }
+
+    pub fn set(&mut self, position: Point, tile: u16) {
+        let index = self
+            .index(position)
+            .unwrap_or_else(|| panic!("invalid position: {position:?}"));
+        self.tiles[index] = tile;
+    }
+}
+
+// The chunk holding `position` and the tile's index within it.
+#[allow(clippy::cast_sign_loss)]
+#[must_use]
+pub fn chunk_location(position: Point) -> ((i32, i32), usize) {
+    let key = (
+        position.x().div_euclid(CHUNK_SIZE),
+        position.y().div_euclid(CHUNK_SIZE),
+    );
+    let x = position.x().rem_euclid(CHUNK_SIZE);
+    let y = position.y().rem_euclid(CHUNK_SIZE);
+    (key, (y * CHUNK_SIZE + x) as usize)
+}
+
+impl ChunkedTiles {
+    #[must_use]
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    #[must_use]
+    pub fn get(&self, position: Point) -> u16 {
+        let (key, index) = chunk_location(position);
+        self.chunks
+            .get(&key)
+            .map_or(tile::EMPTY, |chunk| chunk.tiles[index])
+    }
+
+    pub fn set(&mut self, position: Point, tile: u16) {
+        let (key, index) = chunk_location(position);
+        if tile == tile::EMPTY && !self.chunks.contains_key(&key) {
+            return;
+        }
+
+        let chunk = self.chunks.entry(key).or_insert_with(|| Chunk {
+            tiles: vec![tile::EMPTY; CHUNK_AREA].into_boxed_slice(),
+            occupied: 0,
+        });
+
+        let previous = std::mem::replace(&mut chunk.tiles[index], tile);
+        match (previous == tile::EMPTY, tile == tile::EMPTY) {
+            (true, false) => chunk.occupied += 1,
+            (false, true) => chunk.occupied -= 1,
+            _ => {}
+        }
+
+        if chunk.occupied == 0 {
+            self.chunks.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_tiles_reject_out_of_bounds_positions() {
+        let mut tiles = TileStore::new(TileStorage::Dense, 4, 3);
+        assert_eq!(tiles.storage(), TileStorage::Dense);
+        tiles.set(Point::new(3, 2), tile::FOOD);
+        assert_eq!(tiles.get(Point::new(3, 2)), Some(tile::FOOD));
+        assert_eq!(tiles.get(Point::new(0, 0)), Some(tile::EMPTY));
+        assert_eq!(tiles.get(Point::new(4, 0)), None);
+        assert_eq!(tiles.get(Point::new(0, -1)), None);
+    }
+
+    #[test]
+    fn allocates_and_unloads_chunks() {
+        let mut tiles = ChunkedTiles::default();
+        tiles.set(Point::new(5, 5), tile::EMPTY);
+        assert_eq!(tiles.chunk_count(), 0);
+
+        tiles.set(Point::new(5, 5), tile::WALL);
+        tiles.set(Point::new(6, 5), tile::FOOD);
+        tiles.set(Point::new(-1, -1), tile::WALL);
+        assert_eq!(tiles.chunk_count(), 2);
+        assert_eq!(tiles.get(Point::new(6, 5)), tile::FOOD);
+        assert_eq!(tiles.get(Point::new(-1, -1)), tile::WALL);
+        assert_eq!(tiles.get(Point::new(CHUNK_SIZE, 0)), tile::EMPTY);
+
+        // Overwriting a non-empty tile keeps the chunk occupied.
+        tiles.set(Point::new(5, 5), tile::FOOD);
+        tiles.set(Point::new(6, 5), tile::EMPTY);
+        assert_eq!(tiles.chunk_count(), 2);
+        tiles.set(Point::new(5, 5), tile::EMPTY);
+        tiles.set(Point::new(-1, -1), tile::EMPTY);
+        assert_eq!(tiles.chunk_count(), 0);
+        assert_eq!(tiles.get(Point::new(5, 5)), tile::EMPTY);
+    }
+
+    #[test]
+    fn locates_tiles_within_chunks() {
+        assert_eq!(chunk_location(Point::new(0, 0)), ((0, 0), 0));
+        assert_eq!(chunk_location(Point::new(65, 1)), ((1, 0), 65));
+        assert_eq!(
+            chunk_location(Point::new(-1, -1)),
+            ((-1, -1), CHUNK_AREA - 1)
+        );
+    }
+}
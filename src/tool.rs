@@ -0,0 +1,86 @@
+use sdl2::rect::Point;
+
+use crate::tile;
+
+pub const MAX_BRUSH_SIZE: u32 = 16;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tool {
+    Spawn,
+    Inspect,
+    Wall,
+    Food,
+    Erase,
+    Kill,
+}
+
+impl Tool {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Spawn => "spawn",
+            Tool::Inspect => "inspect",
+            Tool::Wall => "wall",
+            Tool::Food => "food",
+            Tool::Erase => "erase",
+            Tool::Kill => "kill",
+        }
+    }
+
+    #[must_use]
+    pub fn is_brush(self) -> bool {
+        matches!(self, Tool::Wall | Tool::Food | Tool::Erase | Tool::Kill)
+    }
+
+    #[must_use]
+    pub fn paint_tile(self) -> Option<u16> {
+        match self {
+            Tool::Wall => Some(tile::WALL),
+            Tool::Food => Some(tile::FOOD),
+            Tool::Erase => Some(tile::EMPTY),
+            _ => None,
+        }
+    }
+}
+
+// Tiles covered by a round brush centered on `center`; a brush of size 1 covers
+// a single tile and each step up adds a ring around it.
+#[must_use]
+pub fn brush_positions(center: Point, size: u32) -> Vec<Point> {
+    let radius = i32::try_from(size.saturating_sub(1)).unwrap_or(0);
+    let mut positions = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius + radius {
+                positions.push(center.offset(dx, dy));
+            }
+        }
+    }
+
+    positions
+}
+
+// Every tile on the line from `from` to `to`, inclusive, so fast mouse
+// movements still paint connected strokes.
+#[must_use]
+pub fn line_positions(from: Point, to: Point) -> Vec<Point> {
+    let (dx, dy) = ((to.x() - from.x()).abs(), -(to.y() - from.y()).abs());
+    let (sx, sy) = ((to.x() - from.x()).signum(), (to.y() - from.y()).signum());
+    let mut error = dx + dy;
+    let mut position = from;
+    let mut positions = vec![position];
+    while position != to {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            position = position.offset(sx, 0);
+        }
+        if doubled <= dx {
+            error += dx;
+            position = position.offset(0, sy);
+        }
+        positions.push(position);
+    }
+
+    positions
+}
@@ -0,0 +1,224 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use sdl2::pixels::PixelFormatEnum;
+
+use crate::{capture, write_ppm, PixelBuffer, World};
+
+// Writing video to this path sends it to standard output instead.
+pub const STDOUT_PATH: &str = "-";
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum VideoFormat {
+    // YUV4MPEG2 with full-resolution 4:4:4 chroma.
+    #[default]
+    Y4m,
+    // Binary PPM images back to back, e.g. for `ffmpeg -f image2pipe`.
+    Ppm,
+}
+
+impl FromStr for VideoFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "y4m" => Ok(VideoFormat::Y4m),
+            "ppm" => Ok(VideoFormat::Ppm),
+            _ => bail!("expected `y4m` or `ppm`"),
+        }
+    }
+}
+
+pub fn open_output(path: &Path) -> Result<Box<dyn Write>> {
+    if path == Path::new(STDOUT_PATH) {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+
+    let file = File::create(path)
+        .with_context(|| format!("failed to create output file {}", path.display()))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+pub struct NewVideoStreamParams {
+    pub format: VideoFormat,
+    pub world_size: (u16, u16),
+    pub scale: u32,
+    pub frame_skip: u32,
+    // Frames per second of the recorded simulation, before frame skipping.
+    pub fps: u64,
+}
+
+// Streams rendered worlds as uncompressed video frames, keeping one frame out
+// of every `frame_skip + 1` offered.
+pub struct VideoStream<W: Write> {
+    writer: W,
+    format: VideoFormat,
+    scale: u32,
+    frame_skip: u32,
+    offered: u64,
+    pub frames: u64,
+}
+
+impl<W: Write> VideoStream<W> {
+    pub fn new(mut writer: W, params: &NewVideoStreamParams) -> Result<Self> {
+        if params.format == VideoFormat::Y4m {
+            let (width, height) = params.world_size;
+            writeln!(
+                writer,
+                "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444",
+                u32::from(width) * params.scale,
+                u32::from(height) * params.scale,
+                params.fps,
+                u64::from(params.frame_skip) + 1
+            )?;
+        }
+
+        Ok(VideoStream {
+            writer,
+            format: params.format,
+            scale: params.scale,
+            frame_skip: params.frame_skip,
+            offered: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn record(&mut self, world: &World) -> Result<()> {
+        let skip = !self.offered.is_multiple_of(u64::from(self.frame_skip) + 1);
+        self.offered += 1;
+        if skip {
+            return Ok(());
+        }
+
+        let image = capture(world, self.scale)?;
+        match self.format {
+            VideoFormat::Y4m => write_y4m_frame(&image, &mut self.writer)?,
+            VideoFormat::Ppm => write_ppm(&image, &mut self.writer)?,
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Converts RGB24 pixels to studio-range BT.601 Y, U and V planes.
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn write_y4m_frame<W: Write>(image: &PixelBuffer, writer: &mut W) -> Result<()> {
+    ensure!(
+        image.format == PixelFormatEnum::RGB24,
+        "video frames must be RGB24, not {:?}",
+        image.format
+    );
+
+    let pixel_count = image.width as usize * image.height as usize;
+    let mut planes = vec![0u8; pixel_count * 3];
+    let (luma, chroma) = planes.split_at_mut(pixel_count);
+    let (blue_difference, red_difference) = chroma.split_at_mut(pixel_count);
+
+    let row_size = image.width as usize * 3;
+    let pixels = image
+        .data
+        .chunks(image.pitch)
+        .take(image.height as usize)
+        .flat_map(|row| row[..row_size].chunks(3));
+    for (i, pixel) in pixels.enumerate() {
+        let (r, g, b) = (
+            i32::from(pixel[0]),
+            i32::from(pixel[1]),
+            i32::from(pixel[2]),
+        );
+        luma[i] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+        blue_difference[i] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+        red_difference[i] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+    }
+
+    writer.write_all(b"FRAME\n")?;
+    writer.write_all(&planes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::WorldConfig, Color, Map};
+
+    fn world() -> World {
+        World::from_map(
+            &Map::parse("###\n#.#\n###\n").unwrap(),
+            WorldConfig::default(),
+        )
+    }
+
+    fn params(format: VideoFormat) -> NewVideoStreamParams {
+        NewVideoStreamParams {
+            format,
+            world_size: (3, 3),
+            scale: 2,
+            frame_skip: 1,
+            fps: 30,
+        }
+    }
+
+    fn record(format: VideoFormat, offered: usize) -> (u64, Vec<u8>) {
+        let world = world();
+        let mut video = VideoStream::new(Vec::new(), &params(format)).unwrap();
+        for _ in 0..offered {
+            video.record(&world).unwrap();
+        }
+        (video.frames, video.finish().unwrap())
+    }
+
+    #[test]
+    fn writes_y4m_header_and_skipped_frames() {
+        let (frames, output) = record(VideoFormat::Y4m, 3);
+        assert_eq!(frames, 2);
+
+        let header = b"YUV4MPEG2 W6 H6 F30:2 Ip A1:1 C444\n";
+        assert!(output.starts_with(header));
+        let frame_size = b"FRAME\n".len() + 6 * 6 * 3;
+        assert_eq!(output.len(), header.len() + 2 * frame_size);
+        assert!(output[header.len() + frame_size..].starts_with(b"FRAME\n"));
+    }
+
+    #[test]
+    fn writes_ppm_frames_back_to_back() {
+        let (frames, output) = record(VideoFormat::Ppm, 4);
+        assert_eq!(frames, 2);
+
+        let header = b"P6\n6 6\n255\n";
+        let frame_size = header.len() + 6 * 6 * 3;
+        assert_eq!(output.len(), 2 * frame_size);
+        assert!(output.starts_with(header));
+        assert!(output[frame_size..].starts_with(header));
+    }
+
+    #[test]
+    fn converts_rgb_to_studio_range_yuv() {
+        let mut image = PixelBuffer::new(2, 1, PixelFormatEnum::RGB24).unwrap();
+        image.put_pixel(0, 0, Color::RGB(0, 0, 0));
+        image.put_pixel(1, 0, Color::RGB(255, 255, 255));
+        let mut output = Vec::new();
+        write_y4m_frame(&image, &mut output).unwrap();
+        assert_eq!(output, b"FRAME\n\x10\xeb\x80\x80\x80\x80");
+
+        let image = PixelBuffer::new(1, 1, PixelFormatEnum::RGBA32).unwrap();
+        assert!(write_y4m_frame(&image, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!("y4m".parse::<VideoFormat>().unwrap(), VideoFormat::Y4m);
+        assert_eq!("ppm".parse::<VideoFormat>().unwrap(), VideoFormat::Ppm);
+        assert!("mp4".parse::<VideoFormat>().is_err());
+    }
+}
@@ -1,23 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use num::clamp;
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use rand_distr::Normal;
+use rand::{rngs::StdRng, seq::index, Rng, SeedableRng};
+use rand_distr::{Binomial, Normal};
 use sdl2::rect::Point;
 
 use crate::{
     color,
-    config::{PIXEL_FORMAT, SPAWN_INTERVAL},
-    random_wyrm_color, tile, Color, Direction, NewWyrmParams, RelativeDirection, Wyrm,
+    config::{Config, WorldConfig, WyrmStyle},
+    damage::{Damage, DamageTracker},
+    tile, Brain, Color, Death, DeathCause, Direction, Forager, Heatmap, HeatmapKind, Map,
+    NewWyrmParams, RelativeDirection, Renderer, SensedKind, SensorHit, SensorReading, Theme,
+    TileStore, Wyrm, SENSOR_DIRECTIONS,
 };
 
+pub const MAX_RECORDED_DEATHS: usize = 1024;
+const OPEN_POSITION_ATTEMPTS: usize = 64;
+
 pub struct World {
     pub width: u16,
     pub height: u16,
     pub wyrms: HashMap<u16, Wyrm>,
+    pub config: WorldConfig,
+    // `None` unless `heatmaps` is enabled.
+    pub heatmap: Option<Heatmap>,
+    brains: HashMap<u16, Box<dyn Brain>>,
     next_wyrm_id: u16,
-    tiles: Vec<u16>,
+    tiles: TileStore,
+    deaths: VecDeque<Death>,
+    damage: DamageTracker,
     current_step: usize,
     rng: StdRng,
 }
@@ -25,6 +37,7 @@ pub struct World {
 pub struct NewWorldParams {
     pub width: u16,
     pub height: u16,
+    pub config: WorldConfig,
 }
 
 pub type Neighbors = Vec<(RelativeDirection, i8)>;
@@ -32,49 +45,141 @@ pub type Neighbors = Vec<(RelativeDirection, i8)>;
 impl World {
     #[must_use]
     pub fn new(params: &NewWorldParams) -> Self {
-        let tile_count = usize::from(params.width) * usize::from(params.height);
-        let mut world = World {
-            width: params.width,
-            height: params.height,
-            wyrms: HashMap::new(),
-            next_wyrm_id: tile::WYRM,
-            tiles: vec![tile::EMPTY; tile_count],
-            current_step: 0,
-            rng: StdRng::from_entropy(),
-        };
-
+        let mut world = World::empty(params);
         world.fill();
         world
     }
 
-    fn fill(&mut self) {
-        for y in 0..i32::from(self.height) {
-            for x in 0..i32::from(self.width) {
+    pub fn from_config(config: &Config, map: Option<&Map>) -> Result<Self> {
+        if let Some(map) = map {
+            return Ok(World::from_map(map, config.world.clone()));
+        }
+
+        let (width, height) = config.world_size()?;
+        Ok(World::new(&NewWorldParams {
+            width,
+            height,
+            config: config.world.clone(),
+        }))
+    }
+
+    #[must_use]
+    pub fn from_map(map: &Map, config: WorldConfig) -> Self {
+        let mut world = World::empty(&NewWorldParams {
+            width: map.width,
+            height: map.height,
+            config,
+        });
+
+        for y in 0..i32::from(world.height) {
+            for x in 0..i32::from(world.width) {
                 let position = Point::new(x, y);
-                let tile = if self.at_edge(position) {
+                let tile = if world.at_edge(position) {
                     tile::WALL
-                } else if self.rng.gen_bool(1.0 / 16.0) {
-                    tile::FOOD
                 } else {
-                    tile::EMPTY
+                    map.tiles[world.index(position)]
                 };
 
-                self.set_tile(position, tile);
+                world.set_tile(position, tile);
             }
         }
+
+        world
     }
 
-    fn get_tile(&self, position: Point) -> Result<u16> {
-        let index = self.index(position);
+    #[must_use]
+    pub fn to_map(&self) -> Map {
+        let mut tiles = Vec::with_capacity(usize::from(self.width) * usize::from(self.height));
+        for y in 0..i32::from(self.height) {
+            for x in 0..i32::from(self.width) {
+                let tile = self.tiles.get(Point::new(x, y)).unwrap_or(tile::EMPTY);
+                tiles.push(if tile >= tile::WYRM {
+                    tile::EMPTY
+                } else {
+                    tile
+                });
+            }
+        }
+
+        Map {
+            width: self.width,
+            height: self.height,
+            tiles,
+        }
+    }
+
+    fn empty(params: &NewWorldParams) -> Self {
+        let tiles = TileStore::new(params.config.tile_storage, params.width, params.height);
+        let rng = match params.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        World {
+            width: params.width,
+            height: params.height,
+            wyrms: HashMap::new(),
+            config: params.config.clone(),
+            heatmap: params
+                .config
+                .heatmaps
+                .then(|| Heatmap::new(params.width, params.height)),
+            brains: HashMap::new(),
+            next_wyrm_id: tile::WYRM,
+            tiles,
+            deaths: VecDeque::new(),
+            damage: DamageTracker::new(params.width, params.height),
+            current_step: 0,
+            rng,
+        }
+    }
+
+    // Walls the edges and scatters food over the interior. Only the tiles that
+    // get something are visited, so chunked storage stays sparse.
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_possible_wrap)]
+    fn fill(&mut self) {
+        let (width, height) = (i32::from(self.width), i32::from(self.height));
+        let border = i32::from(!self.config.unbounded);
+        if border > 0 {
+            for x in 0..width {
+                self.set_tile(Point::new(x, 0), tile::WALL);
+                self.set_tile(Point::new(x, height - 1), tile::WALL);
+            }
+            for y in 1..height - 1 {
+                self.set_tile(Point::new(0, y), tile::WALL);
+                self.set_tile(Point::new(width - 1, y), tile::WALL);
+            }
+        }
+
+        let margin = 2 * usize::from(!self.config.unbounded);
+        let interior_width = usize::from(self.width).saturating_sub(margin);
+        let interior_height = usize::from(self.height).saturating_sub(margin);
+        let interior = interior_width * interior_height;
+        let food_count = Binomial::new(interior as u64, self.config.food_density)
+            .map_or(0, |distribution| self.rng.sample(distribution) as usize);
+        for i in index::sample(&mut self.rng, interior, food_count) {
+            let x = (i % interior_width) as i32 + border;
+            let y = (i / interior_width) as i32 + border;
+            self.set_tile(Point::new(x, y), tile::FOOD);
+        }
+    }
+
+    pub fn get_tile(&self, position: Point) -> Result<u16> {
+        if !self.contains(position) {
+            return Err(anyhow!("invalid position: {position:?}"));
+        }
+
         self.tiles
-            .get(index)
-            .copied()
+            .get(position)
             .ok_or(anyhow!("invalid position: {position:?}"))
     }
 
     pub fn set_tile(&mut self, position: Point, tile: u16) {
-        let index = self.index(position);
-        self.tiles[index] = tile;
+        self.tiles.set(position, tile);
+        if self.in_bounds(position) {
+            self.damage.mark(position);
+        }
     }
 
     #[allow(clippy::cast_sign_loss)]
@@ -82,13 +187,131 @@ impl World {
         (position.y() * i32::from(self.width) + position.x()) as usize
     }
 
+    // Paints walls, food or empty tiles. Wyrms and the outer walls are left
+    // untouched; returns whether the tile changed.
+    pub fn paint_tile(&mut self, position: Point, tile: u16) -> bool {
+        debug_assert!(tile < tile::WYRM, "can't paint wyrm tiles");
+        if !self.in_interior(position) {
+            return false;
+        }
+
+        match self.get_tile(position) {
+            Ok(current) if current != tile && current < tile::WYRM => {
+                self.set_tile(position, tile);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn kill_wyrm_at(&mut self, position: Point) -> Result<Option<u16>> {
+        match self.get_tile(position) {
+            Ok(wyrm_id) if wyrm_id >= tile::WYRM => {
+                self.kill_wyrm(wyrm_id)?;
+                Ok(Some(wyrm_id))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn kill_wyrm(&mut self, wyrm_id: u16) -> Result<()> {
+        self.destroy_wyrm(wyrm_id, DeathCause::Killed)
+    }
+
+    #[must_use]
+    pub fn contains(&self, position: Point) -> bool {
+        self.config.unbounded || self.in_bounds(position)
+    }
+
+    #[must_use]
+    pub fn in_bounds(&self, position: Point) -> bool {
+        (0..i32::from(self.width)).contains(&position.x())
+            && (0..i32::from(self.height)).contains(&position.y())
+    }
+
+    fn in_interior(&self, position: Point) -> bool {
+        self.contains(position) && !self.at_edge(position)
+    }
+
     fn at_edge(&self, point: Point) -> bool {
+        !self.config.unbounded && self.at_bounds_edge(point)
+    }
+
+    fn at_bounds_edge(&self, point: Point) -> bool {
         point.x == 0
             || point.x == i32::from(self.width) - 1
             || point.y == 0
             || point.y == i32::from(self.height) - 1
     }
 
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if self.config.unbounded {
+            self.width = width;
+            self.height = height;
+            self.damage = DamageTracker::new(width, height);
+            if let Some(heatmap) = &mut self.heatmap {
+                heatmap.resize(width, height);
+            }
+            return;
+        }
+
+        let (old_width, old_height) = (self.width, self.height);
+        let new_tiles = TileStore::new(self.tiles.storage(), width, height);
+        let old_tiles = std::mem::replace(&mut self.tiles, new_tiles);
+        let old_interior = |position: Point| {
+            (1..i32::from(old_width) - 1).contains(&position.x())
+                && (1..i32::from(old_height) - 1).contains(&position.y())
+        };
+
+        self.width = width;
+        self.height = height;
+        self.damage = DamageTracker::new(width, height);
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.resize(width, height);
+        }
+        for y in 0..i32::from(height) {
+            for x in 0..i32::from(width) {
+                let position = Point::new(x, y);
+                let tile = if self.at_edge(position) {
+                    tile::WALL
+                } else if old_interior(position) {
+                    old_tiles.get(position).unwrap_or(tile::EMPTY)
+                } else if self.rng.gen_bool(self.config.food_density) {
+                    tile::FOOD
+                } else {
+                    tile::EMPTY
+                };
+
+                self.set_tile(position, tile);
+            }
+        }
+
+        let mut wyrm_ids: Vec<u16> = self.wyrms.keys().copied().collect();
+        wyrm_ids.sort_unstable();
+        for wyrm_id in wyrm_ids {
+            let wyrm = &self.wyrms[&wyrm_id];
+            let Some(cut) = wyrm.segments.iter().position(|&p| !self.in_interior(p)) else {
+                continue;
+            };
+
+            let removed: Vec<Point> = if cut == 0 {
+                let wyrm = self.wyrms.remove(&wyrm_id).expect("wyrm exists");
+                self.brains.remove(&wyrm_id);
+                self.record_death(&wyrm, DeathCause::OutOfBounds);
+                wyrm.segments.into_iter().collect()
+            } else {
+                let wyrm = self.wyrms.get_mut(&wyrm_id).expect("wyrm exists");
+                wyrm.segments.drain(cut..).collect()
+            };
+
+            for position in removed {
+                if self.in_interior(position) {
+                    self.set_tile(position, tile::EMPTY);
+                }
+            }
+        }
+    }
+
     fn get_next_wyrm_id(&mut self) -> u16 {
         let next_id = self.next_wyrm_id;
         if self.next_wyrm_id == u16::MAX {
@@ -103,56 +326,236 @@ impl World {
         next_id
     }
 
-    pub fn create_wyrm(&mut self, position: Point) -> Result<()> {
-        let current_tile = self.get_tile(position)?;
-        if current_tile == tile::WALL || current_tile >= tile::WYRM {
-            return Ok(());
+    pub fn create_wyrm(&mut self, position: Point) -> Result<Option<u16>> {
+        self.create_wyrm_with_brain(position, Box::new(Forager))
+    }
+
+    pub fn create_wyrm_with_brain(
+        &mut self,
+        position: Point,
+        brain: Box<dyn Brain>,
+    ) -> Result<Option<u16>> {
+        if !self.can_spawn_on(position)? {
+            return Ok(None);
         }
 
-        let id = self.get_next_wyrm_id();
-        let color = random_wyrm_color(&mut self.rng, id)?;
         let direction_index = self.rng.gen_range(0..=3);
         let direction = Direction::try_from(direction_index).unwrap();
-        let wyrm = Wyrm::new(&NewWyrmParams {
+        self.spawn_wyrm(&[position], direction, brain, None)
+            .map(Some)
+    }
+
+    // Creates a wyrm from explicit segments, head first. Every segment must be
+    // orthogonally adjacent to the previous one, and `direction` must not lead
+    // back into the second; returns `None` unless all of them are on empty
+    // tiles.
+    pub fn create_wyrm_from_segments(
+        &mut self,
+        segments: &[Point],
+        direction: Direction,
+    ) -> Result<Option<u16>> {
+        ensure!(!segments.is_empty(), "a wyrm needs at least one segment");
+        ensure!(
+            segments.get(1) != Some(&(segments[0] + direction.into())),
+            "a wyrm can't head into its own neck"
+        );
+        for (i, pair) in segments.windows(2).enumerate() {
+            ensure!(
+                Direction::between(pair[0], pair[1]).is_some(),
+                "segment {} is not adjacent to segment {i}",
+                i + 1
+            );
+        }
+        for (i, segment) in segments.iter().enumerate() {
+            ensure!(
+                !segments[..i].contains(segment),
+                "segment {i} overlaps an earlier segment"
+            );
+            if !self.is_vacant(*segment)? {
+                return Ok(None);
+            }
+        }
+
+        self.spawn_wyrm(segments, direction, Box::new(Forager), None)
+            .map(Some)
+    }
+
+    // Whether a drawn wyrm segment can be placed on the tile, which must be
+    // empty so drawing never overwrites food.
+    pub fn is_vacant(&self, position: Point) -> Result<bool> {
+        Ok(self.get_tile(position)? == tile::EMPTY)
+    }
+
+    // Whether a new wyrm can start on the tile, eating any food there: it
+    // holds neither a wall nor another wyrm.
+    pub fn can_spawn_on(&self, position: Point) -> Result<bool> {
+        let tile = self.get_tile(position)?;
+        Ok(tile != tile::WALL && tile < tile::WYRM)
+    }
+
+    // Places a new wyrm. Without a parent it founds a lineage with a color
+    // from the theme; otherwise it inherits the parent's color with a small
+    // mutation.
+    fn spawn_wyrm(
+        &mut self,
+        segments: &[Point],
+        direction: Direction,
+        brain: Box<dyn Brain>,
+        parent: Option<u16>,
+    ) -> Result<u16> {
+        let id = self.get_next_wyrm_id();
+        let (color, generation, founder, color_drift) = if let Some(parent_id) = parent {
+            let parent = self.get_wyrm(parent_id)?;
+            let (parent_color, (dl, dc, dh)) = (parent.color, parent.color_drift);
+            let (generation, founder) = (parent.generation + 1, parent.founder);
+            let mutation = color::random_mutation(&mut self.rng, self.config.color_mutation)?;
+            let color = color::drift_color(parent_color, mutation);
+            let drift = (dl + mutation.0, dc + mutation.1, dh + mutation.2);
+            (
+                self.config.theme.constrain(color),
+                generation,
+                founder,
+                drift,
+            )
+        } else {
+            let color = self.config.theme.random_wyrm_color(&mut self.rng, id)?;
+            (color, 0, id, (0.0, 0.0, 0.0))
+        };
+
+        let mut wyrm = Wyrm::new(&NewWyrmParams {
             id,
             color,
             direction,
-            position,
+            born_at: self.current_step,
+            parent,
+            generation,
+            founder,
+            color_drift,
+            position: segments[0],
         });
+        wyrm.segments.extend(&segments[1..]);
 
+        for &segment in segments {
+            self.set_tile(segment, id);
+        }
         self.wyrms.insert(id, wyrm);
-        self.set_tile(position, id);
+        self.brains.insert(id, brain);
+        Ok(id)
+    }
+
+    // Splits a wyrm that has grown to `split_length` in two. The back half
+    // becomes its child, heading away from the parent's tail.
+    fn split_wyrm(&mut self, wyrm_id: u16) -> Result<()> {
+        let wyrm = self.get_wyrm_mut(wyrm_id)?;
+        let keep = wyrm.size() - wyrm.size() / 2;
+        let mut segments: Vec<Point> = wyrm.segments.split_off(keep).into();
+        segments.reverse();
+        let direction = match segments[..] {
+            [head, next, ..] => Direction::between(next, head).unwrap_or(wyrm.direction),
+            _ => wyrm.direction,
+        };
+
+        self.spawn_wyrm(&segments, direction, Box::new(Forager), Some(wyrm_id))?;
         Ok(())
     }
 
+    fn mark_wyrm_damaged(&mut self, wyrm_id: u16) -> Result<()> {
+        let segments: Vec<Point> = self.get_wyrm(wyrm_id)?.segments.iter().copied().collect();
+        for segment in segments {
+            if self.in_bounds(segment) {
+                self.damage.mark(segment);
+            }
+        }
+        Ok(())
+    }
+
+    // After a move that kept a wyrm's length, every segment is one place
+    // further from the head; only those now in another shade level changed.
+    fn mark_shade_changes(&mut self, wyrm_id: u16) -> Result<()> {
+        let wyrm = self.get_wyrm(wyrm_id)?;
+        let length = wyrm.size();
+        let changed: Vec<Point> = wyrm
+            .segments
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|&(i, _)| color::shade_level(i, length) != color::shade_level(i - 1, length))
+            .map(|(_, &segment)| segment)
+            .collect();
+        for segment in changed {
+            if self.in_bounds(segment) {
+                self.damage.mark(segment);
+            }
+        }
+        Ok(())
+    }
+
+    // A random interior tile that is vacant along with its four neighbors, so
+    // a wyrm placed there can't die on its first step. Gives up after a few
+    // tries on crowded worlds.
+    pub fn random_open_position(&mut self) -> Result<Option<Point>> {
+        for _ in 0..OPEN_POSITION_ATTEMPTS {
+            let x = self.rng.gen_range(1..i32::from(self.width) - 1);
+            let y = self.rng.gen_range(1..i32::from(self.height) - 1);
+            let position = Point::new(x, y);
+            let mut open = self.can_spawn_on(position)?;
+            for i in 0..4 {
+                let direction = Direction::try_from(i).unwrap();
+                open = open && self.can_spawn_on(position + direction.into())?;
+            }
+            if open {
+                return Ok(Some(position));
+            }
+        }
+
+        Ok(None)
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     fn create_random_wyrm(&mut self) -> Result<()> {
-        let distribution = Normal::new(0.5, 0.1)?;
+        let distribution = Normal::new(self.config.spawn_mean, self.config.spawn_std_dev)?;
         let rx = (self.rng.sample(distribution) * f32::from(self.width)) as i32;
         let ry = (self.rng.sample(distribution) * f32::from(self.height)) as i32;
         let x = clamp(rx, 1, i32::from(self.width) - 2);
         let y = clamp(ry, 1, i32::from(self.height) - 2);
         let position = Point::new(x, y);
-        self.create_wyrm(position)
+        self.create_wyrm(position)?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn current_step(&self) -> usize {
+        self.current_step
     }
 
     pub fn step(&mut self) -> Result<()> {
-        if self.current_step > SPAWN_INTERVAL / 2 && self.current_step % SPAWN_INTERVAL == 0 {
+        let spawn_interval = self.config.spawn_interval;
+        if self.current_step > spawn_interval / 2
+            && self.current_step.is_multiple_of(spawn_interval)
+        {
             self.create_random_wyrm()?;
         }
 
-        let sorted_wyrm_ids: Vec<u16> = self.wyrms.keys().copied().collect();
+        let mut sorted_wyrm_ids: Vec<u16> = self.wyrms.keys().copied().collect();
+        sorted_wyrm_ids.sort_unstable();
         for wyrm_id in sorted_wyrm_ids {
             if self.wyrms.contains_key(&wyrm_id) {
                 self.update_wyrm(wyrm_id)?;
             }
         }
+        if let Some(heatmap) = &mut self.heatmap {
+            for wyrm in self.wyrms.values() {
+                for &segment in &wyrm.segments {
+                    heatmap.record(HeatmapKind::Visits, segment);
+                }
+            }
+        }
 
         self.current_step += 1;
         Ok(())
     }
 
-    fn get_wyrm(&self, wyrm_id: u16) -> Result<&Wyrm> {
+    pub fn get_wyrm(&self, wyrm_id: u16) -> Result<&Wyrm> {
         self.wyrms
             .get(&wyrm_id)
             .ok_or(anyhow!("invalid wyrm ID: {wyrm_id}"))
@@ -165,10 +568,14 @@ impl World {
     }
 
     fn update_wyrm(&mut self, wyrm_id: u16) -> Result<()> {
+        let mut brain = self
+            .brains
+            .remove(&wyrm_id)
+            .ok_or(anyhow!("missing brain for wyrm ID: {wyrm_id}"))?;
         let wyrm = self.get_wyrm(wyrm_id)?;
-        let neighbors = self.get_neighbors(wyrm.head(), wyrm.direction)?;
-        let (relative_direction, _) = neighbors[0];
-        self.do_wyrm_action(wyrm_id, relative_direction)
+        let choice = brain.choose(self, wyrm);
+        self.brains.insert(wyrm_id, brain);
+        self.do_wyrm_action(wyrm_id, choice?)
     }
 
     fn do_wyrm_action(
@@ -182,13 +589,17 @@ impl World {
 
         let tile_id = self.get_tile(destination)?;
         match tile_id {
-            tile::WALL => self.destroy_wyrm(wyrm_id),
+            tile::WALL => self.destroy_wyrm(wyrm_id, DeathCause::Wall),
             tile::EMPTY => {
-                let poop = self.rng.gen_bool(1.0 / 32.0);
+                let poop = self.rng.gen_bool(self.config.poop_chance);
                 self.move_wyrm(wyrm_id, direction, false, poop)
             }
-            tile::FOOD => self.move_wyrm(wyrm_id, direction, true, false),
-            _ if tile_id == wyrm_id => self.destroy_wyrm(wyrm_id),
+            tile::FOOD => {
+                self.record_heat(HeatmapKind::Food, destination);
+                self.get_wyrm_mut(wyrm_id)?.food_eaten += 1;
+                self.move_wyrm(wyrm_id, direction, true, false)
+            }
+            _ if tile_id == wyrm_id => self.destroy_wyrm(wyrm_id, DeathCause::OwnBody),
             enemy_wyrm_id => self.fight_wyrms(wyrm_id, enemy_wyrm_id),
         }?;
 
@@ -216,14 +627,67 @@ impl World {
 
         let wyrm = self.get_wyrm_mut(wyrm_id)?;
         wyrm.direction = direction;
+
+        let split_length = self.config.split_length;
+        let can_reproduce = self
+            .brains
+            .get(&wyrm_id)
+            .is_none_or(|brain| brain.can_reproduce());
+        if grow
+            && can_reproduce
+            && split_length > 0
+            && self.get_wyrm(wyrm_id)?.size() >= split_length
+        {
+            self.split_wyrm(wyrm_id)?;
+        }
+
+        // Segment shades depend on the wyrm's length and each segment's
+        // distance from the head.
+        if self.config.wyrm_style == WyrmStyle::Shaded {
+            if grow {
+                self.mark_wyrm_damaged(wyrm_id)?;
+            } else {
+                self.mark_shade_changes(wyrm_id)?;
+            }
+        }
         Ok(())
     }
 
-    fn destroy_wyrm(&mut self, wyrm_id: u16) -> Result<()> {
+    fn record_death(&mut self, wyrm: &Wyrm, cause: DeathCause) {
+        if self.deaths.len() == MAX_RECORDED_DEATHS {
+            self.deaths.pop_front();
+        }
+
+        self.deaths.push_back(Death {
+            wyrm_id: wyrm.id,
+            step: self.current_step,
+            cause,
+            length: wyrm.size(),
+            food_eaten: wyrm.food_eaten,
+            position: wyrm.head(),
+        });
+        self.record_heat(HeatmapKind::Deaths, wyrm.head());
+    }
+
+    fn record_heat(&mut self, kind: HeatmapKind, position: Point) {
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.record(kind, position);
+        }
+    }
+
+    // Deaths since the last call, oldest first. Only the most recent
+    // `MAX_RECORDED_DEATHS` are kept if nothing collects them.
+    pub fn take_deaths(&mut self) -> Vec<Death> {
+        self.deaths.drain(..).collect()
+    }
+
+    fn destroy_wyrm(&mut self, wyrm_id: u16, cause: DeathCause) -> Result<()> {
         let wyrm = self
             .wyrms
             .remove(&wyrm_id)
             .ok_or(anyhow!("invalid wyrm ID: {wyrm_id}"))?;
+        self.brains.remove(&wyrm_id);
+        self.record_death(&wyrm, cause);
         for (i, position) in wyrm.segments.iter().copied().enumerate() {
             #[allow(clippy::cast_precision_loss)]
             let food_chance = clamp(1.0 / (i as f64 + 1.0) + 0.5, 0.0, 1.0);
@@ -251,20 +715,30 @@ impl World {
             (defender_id, attacker_id)
         };
 
-        self.destroy_wyrm(loser_id)?;
+        self.destroy_wyrm(loser_id, DeathCause::Fight { winner: winner_id })?;
         self.do_wyrm_action(winner_id, RelativeDirection::Forward)
     }
 
     fn get_tile_color(&self, tile: u16) -> Color {
-        match tile {
-            tile::EMPTY => *color::EMPTY,
-            tile::WALL => *color::WALL,
-            tile::FOOD => *color::FOOD,
-            i => self.wyrms.get(&i).map_or(*color::MISSING, |w| w.color),
+        match self.wyrms.get(&tile) {
+            Some(wyrm) => wyrm.color,
+            None => self.config.theme.tile_color(tile),
         }
     }
 
-    fn get_neighbors(&self, position: Point, forward: Direction) -> Result<Neighbors> {
+    // Switches the world's colors, recoloring every living wyrm from the new
+    // theme while keeping each lineage's drift from its founder.
+    pub fn set_theme(&mut self, theme: Theme) -> Result<()> {
+        for wyrm in self.wyrms.values_mut() {
+            let founder_color = theme.recolor_wyrm(wyrm.founder)?;
+            wyrm.color = theme.constrain(color::drift_color(founder_color, wyrm.color_drift));
+        }
+        self.config.theme = theme;
+        self.damage.mark_all();
+        Ok(())
+    }
+
+    pub fn get_neighbors(&self, position: Point, forward: Direction) -> Result<Neighbors> {
         let left = forward.rotate(RelativeDirection::Left);
         let right = forward.rotate(RelativeDirection::Right);
 
@@ -285,20 +759,187 @@ impl World {
         Ok(neighbors)
     }
 
-    pub fn render(&self, pixel_data: &mut [u8]) {
+    #[must_use]
+    pub fn cast_ray(
+        &self,
+        wyrm_id: u16,
+        origin: Point,
+        direction: Direction,
+        range: u16,
+    ) -> Option<SensorHit> {
+        let step: Point = direction.into();
+        let mut position = origin;
+        for distance in 1..=range {
+            position += step;
+            let kind = match self.get_tile(position) {
+                Ok(tile) => SensedKind::from_tile(tile, wyrm_id),
+                Err(_) => Some(SensedKind::Wall),
+            };
+
+            if let Some(kind) = kind {
+                return Some(SensorHit { distance, kind });
+            }
+        }
+
+        None
+    }
+
+    pub fn sense(&self, wyrm_id: u16, range: u16) -> Result<Vec<SensorReading>> {
+        let wyrm = self.get_wyrm(wyrm_id)?;
+        let head = wyrm.head();
+        let readings = SENSOR_DIRECTIONS
+            .iter()
+            .map(|&relative_direction| {
+                let direction = wyrm.direction.rotate(relative_direction);
+                SensorReading {
+                    direction: relative_direction,
+                    hit: self.cast_ray(wyrm_id, head, direction, range),
+                }
+            })
+            .collect();
+        Ok(readings)
+    }
+
+    // The color of a wyrm segment, shaded by its place in the body when the
+    // wyrm style calls for it.
+    #[must_use]
+    pub fn segment_color(&self, wyrm: &Wyrm, index: usize) -> Color {
+        match self.config.wyrm_style {
+            WyrmStyle::Flat => wyrm.color,
+            WyrmStyle::Shaded => {
+                self.config
+                    .theme
+                    .constrain(color::shade_segment(wyrm.color, index, wyrm.size()))
+            }
+        }
+    }
+
+    // The shaded color of every segment of the given wyrms, walking each body
+    // once rather than searching it for every tile drawn.
+    fn shaded_segment_colors<'a>(
+        &self,
+        wyrms: impl Iterator<Item = &'a Wyrm>,
+    ) -> HashMap<Point, Color> {
+        let mut colors = HashMap::new();
+        if self.config.wyrm_style != WyrmStyle::Shaded {
+            return colors;
+        }
+
+        for wyrm in wyrms {
+            for (index, &segment) in wyrm.segments.iter().enumerate() {
+                colors.insert(segment, self.segment_color(wyrm, index));
+            }
+        }
+        colors
+    }
+
+    fn render_tile(
+        &self,
+        position: Point,
+        segment_colors: &HashMap<Point, Color>,
+        renderer: &mut dyn Renderer,
+    ) {
+        let tile = self.tiles.get(position).unwrap_or(tile::EMPTY);
+        let color = match segment_colors.get(&position) {
+            Some(&color) if tile >= tile::WYRM => color,
+            _ => self.get_tile_color(tile),
+        };
+        renderer.draw_tile(position, tile, color);
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        let damage = Damage::Full;
+        let segment_colors = self.shaded_segment_colors(self.wyrms.values());
+        renderer.begin_frame(self.width, self.height, &damage)?;
         for y in 0..i32::from(self.height) {
             for x in 0..i32::from(self.width) {
-                let position = Point::new(x, y);
-                let tile_index = self.index(position);
-                let tile = self.tiles[tile_index];
-                let tile_color = self.get_tile_color(tile);
-                let (r, g, b) = tile_color.rgb();
-
-                let i = tile_index * PIXEL_FORMAT.byte_size_per_pixel();
-                pixel_data[i] = r;
-                pixel_data[i + 1] = g;
-                pixel_data[i + 2] = b;
+                self.render_tile(Point::new(x, y), &segment_colors, renderer);
+            }
+        }
+        renderer.end_frame(&damage)
+    }
+
+    // Only redraws the tiles that changed since the last call, returning the
+    // damaged regions.
+    pub fn render_changes(&mut self, renderer: &mut dyn Renderer) -> Result<Damage> {
+        let damage = self.damage.take(self.width, self.height);
+        match &damage {
+            Damage::Full => self.render(renderer)?,
+            Damage::Partial { tiles, .. } => {
+                let damaged_wyrms: HashSet<u16> = tiles
+                    .iter()
+                    .filter_map(|&position| self.tiles.get(position))
+                    .filter(|&tile| tile >= tile::WYRM)
+                    .collect();
+                let segment_colors = self.shaded_segment_colors(
+                    damaged_wyrms.iter().filter_map(|id| self.wyrms.get(id)),
+                );
+                renderer.begin_frame(self.width, self.height, &damage)?;
+                for &position in tiles {
+                    self.render_tile(position, &segment_colors, renderer);
+                }
+                renderer.end_frame(&damage)?;
             }
         }
+
+        Ok(damage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let map = Map::parse("#######\n#.....#\n#..*..#\n#.....#\n#######\n").unwrap();
+        World::from_map(&map, WorldConfig::default())
+    }
+
+    #[test]
+    fn creates_wyrms_from_segments() {
+        let mut world = world();
+        let segments = [Point::new(2, 1), Point::new(1, 1), Point::new(1, 2)];
+        let id = world
+            .create_wyrm_from_segments(&segments, Direction::Right)
+            .unwrap()
+            .unwrap();
+
+        let wyrm = world.get_wyrm(id).unwrap();
+        assert_eq!(wyrm.head(), Point::new(2, 1));
+        assert_eq!(wyrm.direction, Direction::Right);
+        assert_eq!(wyrm.size(), 3);
+        for segment in segments {
+            assert_eq!(world.get_tile(segment).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn rejects_wyrms_heading_into_their_neck() {
+        let mut world = world();
+        let segments = [Point::new(2, 1), Point::new(1, 1)];
+        let err = world
+            .create_wyrm_from_segments(&segments, Direction::Left)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "a wyrm can't head into its own neck");
+        assert!(world.wyrms.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_segments() {
+        let mut world = world();
+        let gap = [Point::new(1, 1), Point::new(3, 1)];
+        assert!(world
+            .create_wyrm_from_segments(&gap, Direction::Up)
+            .is_err());
+        let overlap = [Point::new(1, 1), Point::new(2, 1), Point::new(1, 1)];
+        assert!(world
+            .create_wyrm_from_segments(&overlap, Direction::Up)
+            .is_err());
+        assert!(world.create_wyrm_from_segments(&[], Direction::Up).is_err());
+
+        // Food isn't drawn over.
+        let on_food = [Point::new(3, 1), Point::new(3, 2)];
+        let created = world.create_wyrm_from_segments(&on_food, Direction::Up);
+        assert_eq!(created.unwrap(), None);
     }
 }
@@ -1,14 +1,22 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt};
 
 use sdl2::rect::Point;
 
-use crate::{Color, Direction};
+use crate::{color::ColorDrift, Color, Direction};
 
 pub struct Wyrm {
     pub id: u16,
     pub color: Color,
     pub direction: Direction,
     pub segments: VecDeque<Point>,
+    pub born_at: usize,
+    pub parent: Option<u16>,
+    pub generation: u32,
+    // The generation-0 ancestor, and how far this wyrm's color has drifted
+    // from its own.
+    pub founder: u16,
+    pub color_drift: ColorDrift,
+    pub food_eaten: u32,
 }
 
 pub struct NewWyrmParams {
@@ -16,6 +24,42 @@ pub struct NewWyrmParams {
     pub color: Color,
     pub direction: Direction,
     pub position: Point,
+    pub born_at: usize,
+    pub parent: Option<u16>,
+    pub generation: u32,
+    pub founder: u16,
+    pub color_drift: ColorDrift,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeathCause {
+    Wall,
+    OwnBody,
+    Fight { winner: u16 },
+    OutOfBounds,
+    Killed,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Death {
+    pub wyrm_id: u16,
+    pub step: usize,
+    pub cause: DeathCause,
+    pub length: usize,
+    pub food_eaten: u32,
+    pub position: Point,
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeathCause::Wall => write!(f, "hit a wall"),
+            DeathCause::OwnBody => write!(f, "ran into itself"),
+            DeathCause::Fight { winner } => write!(f, "lost a fight to wyrm #{winner}"),
+            DeathCause::OutOfBounds => write!(f, "was cut off by a resize"),
+            DeathCause::Killed => write!(f, "was killed by hand"),
+        }
+    }
 }
 
 impl Wyrm {
@@ -29,6 +73,12 @@ impl Wyrm {
             color: params.color,
             direction: params.direction,
             segments,
+            born_at: params.born_at,
+            parent: params.parent,
+            generation: params.generation,
+            founder: params.founder,
+            color_drift: params.color_drift,
+            food_eaten: 0,
         }
     }
 
@@ -39,6 +89,6 @@ impl Wyrm {
 
     #[must_use]
     pub fn head(&self) -> Point {
-        *self.segments.get(0).expect("wyrm is empty")
+        *self.segments.front().expect("wyrm is empty")
     }
 }